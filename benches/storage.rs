@@ -0,0 +1,213 @@
+// Compares the rope-backed `Document` with the previous `Vec<String>` storage
+// that re-segmented a whole line on every edit.
+//
+// Run with `cargo bench`; set `HECTO_BENCH_MB` to change the file size.
+
+use hecto::{Document, Position, Query, SearchOptions};
+
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+
+const EDITS: usize = 10_000;
+const NAIVE_EDITS: usize = 200;
+const LONG_LINE_EDITS: usize = 1_000;
+const LONG_LINE: usize = 4 * 1024 * 1024;
+
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, max: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % max.max(1) as u64) as usize
+    }
+}
+
+#[derive(Default)]
+struct NaiveDocument {
+    rows: Vec<(String, usize)>,
+}
+
+impl NaiveDocument {
+    fn open(path: &Path) -> Self {
+        let contents = fs::read_to_string(path).expect("benchmark file");
+        Self {
+            rows: contents
+                .lines()
+                .map(|line| (String::from(line), line.graphemes(true).count()))
+                .collect(),
+        }
+    }
+
+    fn insert(&mut self, x: usize, y: usize, c: char) {
+        let (row, len) = &mut self.rows[y];
+        let mut result = String::new();
+        let mut inserted = false;
+        for (index, grapheme) in row.graphemes(true).enumerate() {
+            if index == x {
+                result.push(c);
+                inserted = true;
+            }
+            result.push_str(grapheme);
+        }
+        if !inserted {
+            result.push(c);
+        }
+        *len = result.graphemes(true).count();
+        *row = result;
+    }
+
+    fn insert_newline(&mut self, x: usize, y: usize) {
+        let (row, len) = &mut self.rows[y];
+        let head: String = row.graphemes(true).take(x).collect();
+        let tail: String = row.graphemes(true).skip(x).collect();
+        let tail_len = *len - x.min(*len);
+        *len -= tail_len;
+        *row = head;
+        self.rows.insert(y + 1, (tail, tail_len));
+    }
+}
+
+fn generate(megabytes: usize) -> PathBuf {
+    let path = env::temp_dir().join(format!("hecto-bench-{}mb.txt", megabytes));
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() > 0) {
+        return path;
+    }
+    let mut file = BufWriter::new(fs::File::create(&path).expect("benchmark file"));
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    let mut written = 0;
+    let target = megabytes * 1024 * 1024;
+
+    let long_line = "{\"key\":\"value\",\"n\":42,\"ü\":\"ß\"},".repeat(LONG_LINE / 32);
+    writeln!(file, "{}", long_line).expect("benchmark file");
+    written += long_line.len() + 1;
+    while written < target {
+        let line = match rng.below(4) {
+            0 => "    let mut value = compute(&input, index); // naïve café\n".to_owned(),
+            1 => "2026-10-18T12:00:00Z INFO request handled in 12ms status=200\n".to_owned(),
+            2 => "\n".to_owned(),
+            _ => format!("{}\n", "x".repeat(rng.below(160))),
+        };
+        written += line.len();
+        file.write_all(line.as_bytes()).expect("benchmark file");
+    }
+    file.flush().expect("benchmark file");
+    path
+}
+
+fn position(x: usize, y: usize) -> Position {
+    let mut position = Position::default();
+    position.x = x;
+    position.y = y;
+    position
+}
+
+fn report(name: &str, elapsed: Duration, operations: usize) {
+    println!(
+        "{:<40} {:>10.3?} total {:>12.3?} per op",
+        name,
+        elapsed,
+        elapsed / operations.max(1) as u32
+    );
+}
+
+fn bench_rope(path: &Path) {
+    let name = path.to_str().expect("benchmark path");
+
+    let start = Instant::now();
+    let mut document = Document::open(name).expect("benchmark file");
+    report("rope: open", start.elapsed(), 1);
+
+    let lines = document.len();
+    let mut rng = Rng(1);
+    let start = Instant::now();
+    for _ in 0..EDITS {
+        let y = rng.below(lines);
+        let x = rng.below(document.row(y).map_or(0, |row| row.len()) + 1);
+        document.insert(&position(x, y), 'a');
+    }
+    report("rope: insert char at random line", start.elapsed(), EDITS);
+
+    let start = Instant::now();
+    for _ in 0..LONG_LINE_EDITS {
+        let x = rng.below(LONG_LINE / 2);
+        document.insert(&position(x, 0), 'ö');
+    }
+    report("rope: insert char into long line", start.elapsed(), LONG_LINE_EDITS);
+
+    let start = Instant::now();
+    for _ in 0..EDITS {
+        let y = rng.below(document.len());
+        let x = rng.below(document.row(y).map_or(0, |row| row.len()) + 1);
+        document.insert(&position(x, y), '\n');
+    }
+    report("rope: split line", start.elapsed(), EDITS);
+
+    let start = Instant::now();
+    for _ in 0..EDITS {
+        let y = rng.below(document.len().saturating_sub(1));
+        let x = document.row(y).map_or(0, |row| row.len());
+        document.delete(&position(x, y));
+    }
+    report("rope: join lines", start.elapsed(), EDITS);
+
+    let start = Instant::now();
+    for _ in 0..EDITS {
+        let y = rng.below(document.len());
+        black_box(document.row(y).map(|row| row.render(0, 200, 8, &[])));
+    }
+    report("rope: render random row", start.elapsed(), EDITS);
+
+    let start = Instant::now();
+    let query = Query::new("needle that is not there", SearchOptions::default()).expect("valid query");
+    black_box(document.find(&query, &Position::default()));
+    report("rope: find (full scan)", start.elapsed(), 1);
+}
+
+fn bench_naive(path: &Path) {
+    let start = Instant::now();
+    let mut document = NaiveDocument::open(path);
+    report("vec: open", start.elapsed(), 1);
+
+    let lines = document.rows.len();
+    let mut rng = Rng(1);
+    let start = Instant::now();
+    for _ in 0..NAIVE_EDITS {
+        let y = rng.below(lines);
+        let x = rng.below(document.rows[y].1 + 1);
+        document.insert(x, y, 'a');
+    }
+    report("vec: insert char at random line", start.elapsed(), NAIVE_EDITS);
+
+    let start = Instant::now();
+    for _ in 0..NAIVE_EDITS / 10 {
+        let x = rng.below(LONG_LINE / 2);
+        document.insert(x, 0, 'ö');
+    }
+    report("vec: insert char into long line", start.elapsed(), NAIVE_EDITS / 10);
+
+    let start = Instant::now();
+    for _ in 0..NAIVE_EDITS {
+        let y = rng.below(document.rows.len());
+        let x = rng.below(document.rows[y].1 + 1);
+        document.insert_newline(x, y);
+    }
+    report("vec: split line", start.elapsed(), NAIVE_EDITS);
+}
+
+fn main() {
+    let megabytes = env::var("HECTO_BENCH_MB")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(100);
+    let path = generate(megabytes);
+    println!("benchmark file: {} ({} MB)", path.display(), megabytes);
+    bench_rope(&path);
+    bench_naive(&path);
+}
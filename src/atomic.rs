@@ -0,0 +1,138 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Replaces the file at `path` with what `write` produces without ever leaving
+// it half written: the contents go to a temporary file in the same directory,
+// which is synced, given the original's permissions and owner, and renamed
+// over the target.
+pub fn write<F>(path: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    // Write through symbolic links instead of replacing them.
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let original = fs::metadata(&target).ok();
+    // Renaming only needs the directory to be writable, so honour read-only
+    // files and files the user may not write explicitly.
+    if let Some(original) = &original {
+        if original.permissions().readonly() {
+            return Err(Error::new(ErrorKind::PermissionDenied, "file is read-only"));
+        }
+        OpenOptions::new().write(true).open(&target)?;
+    }
+    let mode = original.as_ref().map(mode);
+    replace(&target, original.as_ref(), mode, write)
+}
+
+// Like `write`, for files of the editor's own such as swap files: the file
+// gets `mode` whatever it had before, and keeps the user as its owner.
+pub fn write_with_mode<F>(path: &Path, mode: u32, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    replace(&target, None, Some(mode), write)
+}
+
+fn replace<F>(target: &Path, original: Option<&fs::Metadata>, mode: Option<u32>, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    let temporary = temporary_path(target);
+    // Left behind by an earlier crash of a process with the same id.
+    let _ = fs::remove_file(&temporary);
+
+    let result = write_temporary(&temporary, original, mode, write).and_then(|()| fs::rename(&temporary, target));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+        return result;
+    }
+
+    // Make the rename itself durable; not every file system supports this.
+    if let Some(directory) = target.parent() {
+        let _ = File::open(directory).and_then(|directory| directory.sync_all());
+    }
+    Ok(())
+}
+
+fn write_temporary<F>(
+    temporary: &Path,
+    original: Option<&fs::Metadata>,
+    mode: Option<u32>,
+    write: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    let file = create(temporary, mode)?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|error| error.into_error())?;
+    file.sync_all()?;
+
+    if let Some(original) = original {
+        fs::set_permissions(temporary, original.permissions())?;
+        preserve_owner(temporary, original);
+    }
+    Ok(())
+}
+
+// Creates the temporary file with `mode` from the start, so its contents are
+// never more exposed than the file it replaces while it is being written.
+#[cfg(unix)]
+fn create(temporary: &Path, mode: Option<u32>) -> Result<File, Error> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    if let Some(mode) = mode {
+        options.mode(mode);
+    }
+    options.open(temporary)
+}
+
+#[cfg(not(unix))]
+fn create(temporary: &Path, _mode: Option<u32>) -> Result<File, Error> {
+    OpenOptions::new().write(true).create_new(true).open(temporary)
+}
+
+// The permission bits of a file.
+#[cfg(unix)]
+#[must_use]
+pub fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+#[must_use]
+pub fn mode(_metadata: &fs::Metadata) -> u32 {
+    0o600
+}
+
+// Only a privileged user may give a file away, so failing to is not an error.
+#[cfg(unix)]
+fn preserve_owner(temporary: &Path, original: &fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(current) = fs::metadata(temporary) else {
+        return;
+    };
+    if current.uid() != original.uid() || current.gid() != original.gid() {
+        let _ = std::os::unix::fs::chown(temporary, Some(original.uid()), Some(original.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_temporary: &Path, _original: &fs::Metadata) {}
+
+fn temporary_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{}.{}.hecto.tmp", name, process::id()))
+}
@@ -0,0 +1,120 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[must_use]
+#[allow(clippy::indexing_slicing)]
+pub fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3).saturating_mul(4));
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let triple = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for (index, shift) in [18, 12, 6, 0].iter().enumerate() {
+            if index <= chunk.len() {
+                encoded.push(char::from(BASE64[(triple >> shift & 0x3F) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// The OSC 52 sequence that puts `text` on the system clipboard, or `None` if
+// its base64 payload exceeds `limit` bytes or `limit` is 0.
+#[must_use]
+pub fn osc52(text: &str, limit: usize) -> Option<String> {
+    let encoded = base64(text.as_bytes());
+    (limit > 0 && encoded.len() <= limit).then(|| format!("\x1b]52;c;{}\x07", encoded))
+}
+
+// Pipes `text` into a local clipboard command such as `wl-copy` or
+// `xclip -selection clipboard`.
+#[allow(clippy::missing_errors_doc)]
+pub fn pipe_to_command(command: &str, text: &str) -> Result<(), io::Error> {
+    let mut parts = command.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty clipboard command"))?;
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command may exit without reading everything; its status says
+        // whether it failed.
+        match stdin.write_all(text.as_bytes()) {
+            Err(error) if error.kind() != io::ErrorKind::BrokenPipe => return Err(error),
+            _ => {},
+        }
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} exited with {}", program, status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(encoded: &str) -> Vec<u8> {
+        let mut bits = 0_u32;
+        let mut count = 0;
+        let mut decoded = Vec::new();
+        for byte in encoded.bytes().take_while(|&byte| byte != b'=') {
+            let value = BASE64.iter().position(|&symbol| symbol == byte).unwrap();
+            bits = bits << 6 | u32::try_from(value).unwrap();
+            count += 6;
+            if count >= 8 {
+                count -= 8;
+                decoded.push(u8::try_from(bits >> count & 0xFF).unwrap());
+            }
+        }
+        decoded
+    }
+
+    #[test]
+    fn base64_pads_to_whole_quads() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"hec"), "aGVj");
+        assert_eq!(base64(b"hecto!"), "aGVjdG8h");
+        assert_eq!(base64(b"he"), "aGU=");
+        assert_eq!(base64(b"h"), "aA==");
+        assert_eq!(base64(&[0xFF, 0xFE, 0xFD]), "//79");
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        // Lengths needing no, two and one padding characters.
+        for text in ["", "abc", "naïve", "end\n", "日本語", "\u{1f44d}\u{1f3fd} ok", "x"] {
+            let encoded = base64(text.as_bytes());
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(decode(&encoded), text.as_bytes());
+        }
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&base64(&bytes)), bytes);
+    }
+
+    #[test]
+    fn osc52_respects_the_limit() {
+        assert_eq!(osc52("hi", 4).as_deref(), Some("\x1b]52;c;aGk=\x07"));
+        assert_eq!(osc52("hi", 3), None);
+        assert_eq!(osc52("hi", 0), None);
+        assert_eq!(osc52("", 0), None);
+    }
+
+    #[test]
+    fn pipe_reports_the_exit_status() {
+        assert!(pipe_to_command("cat", "hello").is_ok());
+        // More than a pipe holds, so `false` exits before reading it all.
+        let error = pipe_to_command("false", &"x".repeat(1 << 20)).unwrap_err();
+        assert!(error.to_string().starts_with("false exited with"));
+        assert_eq!(pipe_to_command("", "x").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,170 @@
+use std::env;
+use std::fs;
+
+// Replaces a leading `~` with the home directory.
+#[must_use]
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => path.to_owned(),
+    }
+}
+
+// Completes the last component of `path` as far as the files it may name
+// agree, and returns the completed path with those names, sorted. Names of
+// directories end in a slash, and hidden files only match a leading dot.
+#[must_use]
+pub fn complete(path: &str) -> (String, Vec<String>) {
+    if path == "~" {
+        return ("~/".to_owned(), Vec::new());
+    }
+    let (directory, prefix) = path
+        .rfind('/')
+        .map_or(("", path), |slash| path.split_at(slash.saturating_add(1)));
+    let search = if directory.is_empty() {
+        ".".to_owned()
+    } else {
+        expand_home(directory)
+    };
+    let Ok(entries) = fs::read_dir(search) else {
+        return (path.to_owned(), Vec::new());
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let mut name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            // Follows symbolic links to directories too.
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            Some(name)
+        })
+        .collect();
+    names.sort();
+    let completed = format!("{}{}", directory, common_prefix(&names).unwrap_or(prefix));
+    (completed, names)
+}
+
+fn common_prefix(names: &[String]) -> Option<&str> {
+    let (first, rest) = names.split_first()?;
+    let len = rest.iter().fold(first.len(), |len, name| {
+        first
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(name.len()), |((index, _), _)| len.min(index))
+    });
+    first.get(..len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A directory holding `files` and `directories`, removed when dropped.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, files: &[&str], directories: &[&str]) -> Self {
+            let path = env::temp_dir().join(format!("hecto-completion-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&path).unwrap();
+            for file in files {
+                fs::write(path.join(file), "").unwrap();
+            }
+            for directory in directories {
+                fs::create_dir(path.join(directory)).unwrap();
+            }
+            Self(path)
+        }
+
+        fn path(&self, name: &str) -> String {
+            format!("{}/{}", self.0.display(), name)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn completes_to_the_common_prefix() {
+        let fixture = Fixture::new("prefix", &["report-2023.txt", "report-2024.txt", "notes.md"], &[]);
+        let (completed, names) = complete(&fixture.path("rep"));
+        assert_eq!(completed, fixture.path("report-202"));
+        assert_eq!(names, ["report-2023.txt", "report-2024.txt"]);
+        let (completed, names) = complete(&fixture.path("n"));
+        assert_eq!(completed, fixture.path("notes.md"));
+        assert_eq!(names, ["notes.md"]);
+    }
+
+    #[test]
+    fn keeps_the_path_without_matches() {
+        let fixture = Fixture::new("none", &["a.txt"], &[]);
+        assert_eq!(complete(&fixture.path("z")), (fixture.path("z"), Vec::new()));
+        let missing = fixture.path("missing/a");
+        assert_eq!(complete(&missing), (missing, Vec::new()));
+    }
+
+    #[test]
+    fn directories_end_in_a_slash() {
+        let fixture = Fixture::new("slash", &["src.txt"], &["src"]);
+        let (completed, names) = complete(&fixture.path("s"));
+        assert_eq!(completed, fixture.path("src"));
+        assert_eq!(names, ["src.txt", "src/"]);
+        let (completed, names) = complete(&fixture.path("src/"));
+        assert_eq!(completed, fixture.path("src/"));
+        assert!(names.is_empty());
+    }
+
+    #[test]
+    fn hidden_files_need_a_leading_dot() {
+        let fixture = Fixture::new("hidden", &[".profile", "plan"], &[]);
+        assert_eq!(complete(&fixture.path("")).1, ["plan"]);
+        assert_eq!(complete(&fixture.path(".")).1, [".profile"]);
+    }
+
+    #[test]
+    fn common_prefix_stops_at_characters() {
+        let names = ["café".to_owned(), "cafè".to_owned()];
+        assert_eq!(common_prefix(&names), Some("caf"));
+        assert_eq!(common_prefix(&[]), None);
+    }
+
+    #[test]
+    fn expands_the_home_directory() {
+        let Ok(home) = env::var("HOME") else {
+            return;
+        };
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~/notes.txt"), format!("{}/notes.txt", home));
+        // Other users' homes and later tildes are left alone.
+        assert_eq!(expand_home("~root/x"), "~root/x");
+        assert_eq!(expand_home("a/~/b"), "a/~/b");
+        assert_eq!(expand_home("/tmp/x"), "/tmp/x");
+    }
+
+    #[test]
+    fn completes_below_the_home_directory() {
+        assert_eq!(complete("~"), ("~/".to_owned(), Vec::new()));
+        let Ok(home) = env::var("HOME") else {
+            return;
+        };
+        let mut expected: Vec<String> = fs::read_dir(&home)
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        expected.sort();
+        let (completed, names) = complete("~/");
+        assert!(completed.starts_with("~/"));
+        let mut names: Vec<String> = names.into_iter().map(|name| name.trim_end_matches('/').to_owned()).collect();
+        names.sort();
+        assert_eq!(names, expected);
+    }
+}
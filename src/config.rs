@@ -0,0 +1,102 @@
+use std::env;
+use std::time::Duration;
+
+const OSC52_LIMIT: usize = 100_000;
+const TAB_WIDTH: usize = 8;
+const SWAP_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineNumbers {
+    Off,
+    Absolute,
+    // Distances from the cursor line, which itself shows its absolute number.
+    Relative,
+}
+
+impl LineNumbers {
+    // The mode after this one when cycling through them.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Off,
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(Self::Off),
+            "absolute" | "on" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            _ => None,
+        }
+    }
+}
+
+// Settings read from `HECTO_*` environment variables.
+pub struct Config {
+    // Largest base64 payload sent to the terminal clipboard; 0 disables OSC 52.
+    pub osc52_limit: usize,
+    // Command that receives copied text on stdin, e.g. `xclip -selection clipboard`.
+    pub clipboard_command: Option<String>,
+    // Gutter shown left of the text: `off`, `absolute` or `relative`.
+    pub line_numbers: LineNumbers,
+    // Columns between tab stops.
+    pub tab_width: usize,
+    // Whether Tab inserts spaces up to the next tab stop instead of a tab.
+    pub expand_tabs: bool,
+    // Least time between writes of the swap file holding unsaved changes.
+    pub swap_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            osc52_limit: OSC52_LIMIT,
+            clipboard_command: None,
+            line_numbers: LineNumbers::Off,
+            tab_width: TAB_WIDTH,
+            expand_tabs: false,
+            swap_interval: SWAP_INTERVAL,
+        }
+    }
+}
+
+impl Config {
+    #[must_use]
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            osc52_limit: var("HECTO_OSC52_LIMIT")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default.osc52_limit),
+            clipboard_command: var("HECTO_CLIPBOARD_COMMAND").or(default.clipboard_command),
+            line_numbers: var("HECTO_LINE_NUMBERS")
+                .and_then(|value| LineNumbers::parse(&value))
+                .unwrap_or(default.line_numbers),
+            tab_width: var("HECTO_TAB_WIDTH")
+                .and_then(|value| value.parse().ok())
+                .filter(|&width| width > 0)
+                .unwrap_or(default.tab_width),
+            expand_tabs: var("HECTO_EXPAND_TABS")
+                .and_then(|value| parse_flag(&value))
+                .unwrap_or(default.expand_tabs),
+            swap_interval: var("HECTO_SWAP_INTERVAL_MS")
+                .and_then(|value| value.parse().ok())
+                .map_or(default.swap_interval, Duration::from_millis),
+        }
+    }
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
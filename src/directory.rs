@@ -0,0 +1,162 @@
+use std::env;
+use std::fs::{self, Metadata};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+// A file or directory in a listing.
+pub struct Entry {
+    pub name: String,
+    // Whether it is a directory or a link to one.
+    pub is_dir: bool,
+    pub is_link: bool,
+    pub executable: bool,
+    pub size: u64,
+}
+
+impl Entry {
+    // The character `ls -F` would add to the name.
+    fn marker(&self) -> &'static str {
+        if self.is_link {
+            "@"
+        } else if self.is_dir {
+            "/"
+        } else if self.executable {
+            "*"
+        } else {
+            ""
+        }
+    }
+}
+
+// The contents of a directory: `..` first unless it is the root, then the
+// directories and then the files, each by name.
+pub struct Listing {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Listing {
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let path = fs::canonicalize(path)?;
+        let mut entries: Vec<Entry> = fs::read_dir(&path)?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let is_link = metadata.file_type().is_symlink();
+                // Links are described by what they point to, if anything.
+                let target = if is_link {
+                    fs::metadata(entry.path()).unwrap_or(metadata)
+                } else {
+                    metadata
+                };
+                Some(Entry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_dir: target.is_dir(),
+                    is_link,
+                    executable: is_executable(&target),
+                    size: target.len(),
+                })
+            })
+            .collect();
+        entries.sort_by_cached_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+        if path.parent().is_some() {
+            entries.insert(0, Entry {
+                name: "..".to_owned(),
+                is_dir: true,
+                is_link: false,
+                executable: false,
+                size: 0,
+            });
+        }
+        Ok(Self { path, entries })
+    }
+
+    // The directory, as an absolute path.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn entry(&self, index: usize) -> Option<&Entry> {
+        self.entries.get(index)
+    }
+
+    #[must_use]
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    // One line per entry, with its size and type marker.
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let size = if entry.is_dir {
+                    String::new()
+                } else {
+                    format_size(entry.size)
+                };
+                // A line break in a name would split its line.
+                let name = entry.name.replace(['\n', '\r'], "?");
+                format!("{:>6}  {}{}\n", size, name, entry.marker())
+            })
+            .collect()
+    }
+}
+
+// How to refer to `path`: relative to the working directory when it is inside
+// it, and as it is otherwise.
+#[must_use]
+pub fn display(path: &Path) -> String {
+    let relative = env::current_dir()
+        .and_then(fs::canonicalize)
+        .ok()
+        .and_then(|current| path.strip_prefix(current).ok().map(Path::to_path_buf));
+    match relative {
+        Some(relative) if relative.as_os_str().is_empty() => ".".to_owned(),
+        Some(relative) => relative.to_string_lossy().into_owned(),
+        None => path.to_string_lossy().into_owned(),
+    }
+}
+
+// Whether `name` names an entry of the listed directory itself, rather than
+// the directory, its parent or a path elsewhere.
+#[must_use]
+pub fn is_entry_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('/') && name != "." && name != ".."
+}
+
+// Sizes in bytes below a kilobyte, and with one decimal below ten otherwise,
+// such as `512`, `1.5K` or `24M`.
+#[allow(clippy::cast_precision_loss)]
+fn format_size(size: u64) -> String {
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64;
+    for unit in ["K", "M", "G", "T"] {
+        value /= 1024.0;
+        if value < 1024.0 || unit == "T" {
+            return if value < 10.0 {
+                format!("{:.1}{}", value, unit)
+            } else {
+                format!("{:.0}{}", value, unit)
+            };
+        }
+    }
+    size.to_string()
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &Metadata) -> bool {
+    false
+}
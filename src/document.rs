@@ -1,29 +1,335 @@
+use super::history::{Change, ChangeKind};
+use super::FileType;
+use super::History;
 use super::Position;
-use super::Row;
+use super::Query;
+use super::Rope;
+use super::{LineEnding, Row};
+
+use super::atomic;
+use super::directory::{self, Listing};
+use super::Encoding;
+use super::hex::HexBytes;
+use super::swap;
 
 use std::fs;
-use std::io::{Error, Write};
+use std::io::{Error, ErrorKind, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-#[derive(Default)]
 pub struct Document {
-    rows: Vec<Row>,
+    rows: Rope,
     pub file_name: Option<String>,
-    dirty: bool,
+    history: History,
+    file_type: FileType,
+    // Rows before this index have up-to-date highlighting.
+    highlighted: usize,
+    // Line break given to new rows; rows keep their own when the file mixes them.
+    line_ending: LineEnding,
+    mixed_endings: bool,
+    final_newline: bool,
+    bom: bool,
+    encoding: Encoding,
+    // The file looked like binary data; it is shown one character per byte.
+    binary: bool,
+    // Set for changes the history does not track, such as converted line
+    // endings or recovered text, until the next save.
+    unsaved: bool,
+    // Bumped on every change, so the swap file is only rewritten when needed.
+    revision: u64,
+    swap_revision: u64,
+    // A swap file was left behind by an earlier session.
+    swap_found: bool,
+    // Opened alongside another session's swap file, so neither it nor the
+    // file itself may be written.
+    read_only: bool,
+    // The bytes being edited while the document is shown in hex; they replace
+    // the rows until hex mode is left.
+    hex: Option<HexBytes>,
+    // Set when the document lists a directory rather than holding a file.
+    directory: Option<Listing>,
+    // The first row changed since `take_edited` was last called.
+    edited: Option<usize>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            rows: Rope::default(),
+            file_name: None,
+            history: History::default(),
+            file_type: FileType::default(),
+            highlighted: 0,
+            line_ending: LineEnding::default(),
+            mixed_endings: false,
+            final_newline: true,
+            bom: false,
+            encoding: Encoding::default(),
+            binary: false,
+            unsaved: false,
+            revision: 0,
+            swap_revision: 0,
+            swap_found: false,
+            read_only: false,
+            hex: None,
+            directory: None,
+            edited: None,
+        }
+    }
 }
 
 impl Document {
     pub fn open(filename: &str) -> Result<Self, std::io::Error> {
-        let contents = fs::read_to_string(filename)?;
-        let mut rows = Vec::new();
-        for value in contents.lines() {
-            rows.push(Row::from(value));
+        if fs::metadata(filename)?.is_dir() {
+            return Self::open_directory(Path::new(filename));
         }
-        
-        Ok(Self {
-            rows,
+        let decoded = Encoding::detect(&fs::read(filename)?);
+        let mut document = Self {
             file_name: Some(filename.to_string()),
-            dirty: false,
-        })
+            file_type: FileType::from(filename),
+            encoding: decoded.encoding,
+            binary: decoded.binary,
+            swap_found: swap::path(filename).exists(),
+            ..Self::default()
+        };
+        document.load(&decoded.text);
+        Ok(document)
+    }
+
+    // Replaces the rows with `contents`, noting its line endings and BOM.
+    fn load(&mut self, contents: &str) {
+        let (bom, contents) = match contents.strip_prefix('\u{feff}') {
+            Some(contents) => (true, contents),
+            None => (false, contents),
+        };
+        let final_newline = contents.is_empty() || contents.ends_with('\n');
+
+        let mut rows = Vec::new();
+        let mut crlf = 0_usize;
+        for line in contents.split_inclusive('\n') {
+            let (text, ending) = if let Some(text) = line.strip_suffix("\r\n") {
+                crlf = crlf.saturating_add(1);
+                (text, LineEnding::Crlf)
+            } else {
+                (line.strip_suffix('\n').unwrap_or(line), LineEnding::Lf)
+            };
+            let mut row = Row::from(text);
+            row.set_ending(ending);
+            rows.push(row);
+        }
+        let breaks = if final_newline { rows.len() } else { rows.len().saturating_sub(1) };
+        let lf = breaks.saturating_sub(crlf);
+        let line_ending = if crlf > lf { LineEnding::Crlf } else { LineEnding::Lf };
+        // An unterminated last row gets a break only if one is added after it.
+        if !final_newline {
+            if let Some(row) = rows.last_mut() {
+                row.set_ending(line_ending);
+            }
+        }
+
+        self.rows = Rope::from(rows);
+        self.edited = Some(0);
+        self.highlighted = 0;
+        self.line_ending = line_ending;
+        self.mixed_endings = crlf > 0 && lf > 0;
+        self.final_newline = final_newline;
+        self.bom = bom;
+    }
+
+    // The encoding used when saving, or `binary` for binary files.
+    pub fn encoding(&self) -> &'static str {
+        if self.binary {
+            "binary"
+        } else {
+            self.encoding.name()
+        }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    // Saves the text in `encoding` from now on.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        if encoding == self.encoding && !self.binary {
+            return;
+        }
+        self.encoding = encoding;
+        self.bom = encoding.needs_bom() || (self.bom && encoding == Encoding::Utf8);
+        self.binary = false;
+        self.unsaved = true;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    // Lists the directory at `path`, one entry per row.
+    pub fn open_directory(path: &Path) -> Result<Self, Error> {
+        let listing = Listing::read(path)?;
+        let mut document = Self {
+            file_name: Some(directory::display(listing.path())),
+            ..Self::default()
+        };
+        document.load(&listing.text());
+        document.directory = Some(listing);
+        Ok(document)
+    }
+
+    pub fn directory(&self) -> Option<&Listing> {
+        self.directory.as_ref()
+    }
+
+    pub fn hex(&self) -> Option<&HexBytes> {
+        self.hex.as_ref()
+    }
+
+    pub fn hex_mut(&mut self) -> Option<&mut HexBytes> {
+        self.hex.as_mut()
+    }
+
+    // Shows the document as the bytes it would be saved as.
+    pub fn enter_hex(&mut self) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        self.write_contents(&mut bytes, self.encoding)?;
+        self.hex = Some(HexBytes::new(bytes));
+        Ok(())
+    }
+
+    // Returns to the text, decoding the bytes again if they were edited. Text
+    // edits made before can no longer be undone then.
+    pub fn leave_hex(&mut self) {
+        let Some(hex) = self.hex.take() else {
+            return;
+        };
+        if hex.is_modified() {
+            self.load_bytes(hex.as_bytes());
+            self.unsaved = true;
+        }
+    }
+
+    fn load_bytes(&mut self, bytes: &[u8]) {
+        let text = match self.encoding.decode(bytes) {
+            Some(text) if !self.binary => text,
+            _ => {
+                let decoded = Encoding::detect(bytes);
+                self.encoding = decoded.encoding;
+                self.binary = decoded.binary;
+                decoded.text
+            },
+        };
+        self.load(&text);
+        self.history = History::default();
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    pub fn has_swap(&self) -> bool {
+        self.swap_found
+    }
+
+    // The id of the running session that is editing the file too, if the swap
+    // file found on open is one of its own.
+    pub fn swap_owner(&self) -> Option<u32> {
+        self.swap_path().filter(|_| self.swap_found).and_then(|path| swap::running_owner(&path))
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // Leaves the swap file found on open alone and refuses to save.
+    pub fn set_read_only(&mut self) {
+        self.read_only = true;
+        self.swap_found = false;
+    }
+
+    // Replaces the text with the one kept in the swap file found on open. The
+    // result counts as unsaved and cannot be undone.
+    pub fn recover(&mut self) -> Result<(), Error> {
+        let Some(path) = self.swap_path() else {
+            return Ok(());
+        };
+        let contents = swap::read(&path)?;
+        self.load(&contents);
+        self.history = History::default();
+        self.unsaved = true;
+        self.swap_found = false;
+        self.revision = self.revision.wrapping_add(1);
+        Ok(())
+    }
+
+    pub fn discard_swap(&mut self) -> Result<(), Error> {
+        self.swap_found = false;
+        self.swap_path().map_or(Ok(()), |path| swap::remove(&path))
+    }
+
+    fn swap_path(&self) -> Option<PathBuf> {
+        self.file_name.as_deref().map(swap::path)
+    }
+
+    // Brings the swap file in line with the text: written while there are
+    // unsaved changes and removed once there are none.
+    pub fn update_swap(&mut self) -> Result<(), Error> {
+        // Swap files hold text, so bytes edited in hex are not kept.
+        if self.read_only
+            || self.swap_found
+            || self.hex.is_some()
+            || self.directory.is_some()
+            || self.revision == self.swap_revision
+        {
+            return Ok(());
+        }
+        let Some(file_name) = self.file_name.as_deref() else {
+            return Ok(());
+        };
+        if self.is_dirty() {
+            // Swap files are always UTF-8, whatever the file is saved in.
+            swap::write(file_name, |file| self.write_contents(file, Encoding::Utf8).map(|_| ()))?;
+        } else {
+            swap::remove(&swap::path(file_name))?;
+        }
+        self.swap_revision = self.revision;
+        Ok(())
+    }
+
+    // Removes the swap file unless it belongs to another session.
+    pub fn remove_swap(&mut self) -> Result<(), Error> {
+        if self.read_only || self.swap_found || self.directory.is_some() {
+            return Ok(());
+        }
+        self.swap_revision = self.revision;
+        self.swap_path().map_or(Ok(()), |path| swap::remove(&path))
+    }
+
+    pub fn file_type(&self) -> String {
+        self.file_type.name()
+    }
+
+    // The line-ending style: `LF`, `CRLF` or `Mixed`.
+    pub fn line_endings(&self) -> &'static str {
+        if self.mixed_endings {
+            "Mixed"
+        } else {
+            self.line_ending.name()
+        }
+    }
+
+    // Gives every row the same line break; saved on the next save.
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        for y in 0..self.rows.len() {
+            if let Some(row) = self.rows.get_mut(y) {
+                if row.ending() != ending {
+                    row.set_ending(ending);
+                    self.unsaved = true;
+                    self.revision = self.revision.wrapping_add(1);
+                }
+            }
+        }
+        self.line_ending = ending;
+        self.mixed_endings = false;
+    }
+
+    fn new_row(&self) -> Row {
+        let mut row = Row::default();
+        row.set_ending(self.line_ending);
+        row
     }
 
     pub fn row(&self, index: usize) -> Option<&Row> {
@@ -43,78 +349,605 @@ impl Document {
             return;
         }
         if pos.y == self.rows.len() {
-            self.rows.push(Row::default());
+            self.rows.push(self.new_row());
             return;
         }
-        #[allow(clippy::indexing_slicing)]
-        let new_row = self.rows[pos.y].split(pos.x);
-        #[allow(clippy::integer_arithmetic)]
-        self.rows.insert(pos.y + 1, new_row);
+        if let Some(row) = self.rows.get_mut(pos.y) {
+            let new_row = row.split(pos.x);
+            #[allow(clippy::arithmetic_side_effects)]
+            self.rows.insert(pos.y + 1, new_row);
+        }
     }
 
     pub fn insert(&mut self, pos: &Position, c: char) {
         if pos.y > self.rows.len() {
             return;
         }
-        self.dirty = true;
-        if c == '\n' {
-            self.insert_newline(pos);
+        let mut text = String::new();
+        text.push(c);
+        self.record_insert(pos, &text, false);
+    }
+
+    pub fn delete(&mut self, pos: &Position) {
+        self.delete_at(pos, pos);
+    }
+
+    // Deletes the grapheme (or line break) before `pos` and returns the position
+    // the cursor should move to.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn backspace(&mut self, pos: &Position) -> Option<Position> {
+        let previous = if pos.x > 0 {
+            Position { x: pos.x - 1, y: pos.y }
+        } else if pos.y > 0 {
+            let y = pos.y - 1;
+            Position { x: self.row(y).map_or(0, Row::len), y }
+        } else {
+            return None;
+        };
+        self.delete_at(&previous, pos);
+        Some(previous)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn delete_at(&mut self, pos: &Position, cursor: &Position) {
+        let len = self.rows.len();
+        let Some(row) = self.rows.get(pos.y) else {
             return;
+        };
+        let end = if pos.x >= row.len() {
+            if pos.y + 1 >= len {
+                return;
+            }
+            Position { x: 0, y: pos.y + 1 }
+        } else {
+            Position { x: pos.x + 1, y: pos.y }
+        };
+        let text = self.remove_text(pos, &end);
+        self.history.record(Change {
+            kind: ChangeKind::Delete,
+            start: pos.clone(),
+            end,
+            text,
+            cursor_before: cursor.clone(),
+            cursor_after: pos.clone(),
+            joined: false,
+            appended: false,
+        });
+    }
+
+    // Inserts `text`, which may span several lines, as its own undo step and
+    // returns the position after it.
+    pub fn insert_str(&mut self, pos: &Position, text: &str) -> Position {
+        if pos.y > self.rows.len() || text.is_empty() {
+            return pos.clone();
         }
-        if pos.y == self.rows.len() {
-            let mut row = Row::default();
-            row.insert(0, c);
-            self.rows.push(row);
+        self.history.seal();
+        let end = self.record_insert(pos, text, false);
+        self.history.seal();
+        end
+    }
+
+    // Deletes the text between `start` and `end` as a single undo step and
+    // returns it.
+    pub fn delete_range(&mut self, start: &Position, end: &Position) -> String {
+        let text = self.remove_text(start, end);
+        if !text.is_empty() {
+            self.history.record(Change {
+                kind: ChangeKind::Delete,
+                start: start.clone(),
+                end: end.clone(),
+                text: text.clone(),
+                cursor_before: end.clone(),
+                cursor_after: start.clone(),
+                joined: false,
+                appended: false,
+            });
+        }
+        text
+    }
+
+    // Returns the text between `start` and `end`, joining rows with newlines.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn text(&self, start: &Position, end: &Position) -> String {
+        let mut text = String::new();
+        for (y, row) in (start.y..=end.y).zip(self.rows.iter_from(start.y)) {
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { row.len() };
+            if y > start.y {
+                text.push('\n');
+            }
+            text.push_str(row.text(from..to));
+        }
+        text
+    }
+
+    // Replaces `len` graphemes at `pos` with `text` as a single undo step, or
+    // as part of the step before when `joined`, and returns the position after
+    // the inserted text.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn replace(&mut self, pos: &Position, len: usize, text: &str, mut joined: bool) -> Position {
+        let end = Position { x: pos.x + len, y: pos.y };
+        let removed = self.remove_text(pos, &end);
+        self.history.seal();
+        if !removed.is_empty() {
+            self.history.record(Change {
+                kind: ChangeKind::Delete,
+                start: pos.clone(),
+                end,
+                text: removed,
+                cursor_before: pos.clone(),
+                cursor_after: pos.clone(),
+                joined,
+                appended: false,
+            });
+            joined = true;
+        }
+        let new_end = if text.is_empty() {
+            pos.clone()
         } else {
-            #[allow(clippy::indexing_slicing)]
-            let row = &mut self.rows[pos.y];
-            row.insert(pos.x, c);
+            self.record_insert(pos, text, joined)
+        };
+        self.history.seal();
+        new_end
+    }
+
+    // Inserts `text` at `pos` and records it, returning the position after it.
+    // Text that combines with the graphemes around it, such as a combining mark
+    // typed after a letter, is recorded as replacing those graphemes, so that
+    // undoing it removes all of the text.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn record_insert(&mut self, pos: &Position, text: &str, joined: bool) -> Position {
+        let appended = pos.y == self.rows.len();
+        let previous = Position {
+            x: pos.x.saturating_sub(1),
+            y: pos.y,
+        };
+        let before = self.text(&previous, pos).len();
+        let covered = self.insert_text(pos, text);
+        let inserted = self.text(&covered.start, &covered.end);
+        if covered.start == *pos && inserted == text {
+            self.history.record(Change {
+                kind: ChangeKind::Insert,
+                start: pos.clone(),
+                end: covered.end.clone(),
+                text: text.to_owned(),
+                cursor_before: pos.clone(),
+                cursor_after: covered.end.clone(),
+                joined,
+                appended,
+            });
+            return covered.end;
         }
+        let prefix = if covered.start == *pos { 0 } else { before };
+        let head = inserted.get(..prefix).unwrap_or_default();
+        let tail = inserted.get(prefix + text.len()..).unwrap_or_default();
+        self.history.record(Change {
+            kind: ChangeKind::Delete,
+            start: covered.start.clone(),
+            end: Position {
+                x: covered.start.x + usize::from(!head.is_empty()) + usize::from(!tail.is_empty()),
+                y: covered.start.y,
+            },
+            text: format!("{}{}", head, tail),
+            cursor_before: pos.clone(),
+            cursor_after: covered.start.clone(),
+            joined,
+            appended: false,
+        });
+        self.history.record(Change {
+            kind: ChangeKind::Insert,
+            start: covered.start.clone(),
+            end: covered.end.clone(),
+            text: inserted,
+            cursor_before: covered.start,
+            cursor_after: covered.end.clone(),
+            joined: true,
+            appended,
+        });
+        covered.end
     }
 
-    #[allow(clippy::integer_arithmetic, clippy::indexing_slicing)]
-    pub fn delete(&mut self, pos: &Position) {
-        let len = self.rows.len();
-        if pos.y >= len {
-            return;
+    // Inserts `text` at `pos` without recording it and returns the positions
+    // around the graphemes now holding it, which reach further than the text
+    // when it combines with its neighbours.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn insert_text(&mut self, pos: &Position, text: &str) -> Range<Position> {
+        self.unhighlight_rows(pos.y);
+        self.mark_edited(pos.y);
+        self.revision = self.revision.wrapping_add(1);
+        let mut start = pos.clone();
+        let mut pos = pos.clone();
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.insert_newline(&pos);
+                pos = Position { x: 0, y: pos.y + 1 };
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if pos.y == self.rows.len() {
+                self.rows.push(self.new_row());
+            }
+            let Some(row) = self.rows.get_mut(pos.y) else {
+                break;
+            };
+            let covered = row.insert_str(pos.x, line);
+            if index == 0 {
+                start.x = covered.start;
+            }
+            pos.x = covered.end;
+        }
+        start..pos
+    }
+
+    // Removes the text between `start` and `end` without recording it and
+    // returns what was removed.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn remove_text(&mut self, start: &Position, end: &Position) -> String {
+        self.unhighlight_rows(start.y);
+        self.mark_edited(start.y);
+        self.revision = self.revision.wrapping_add(1);
+        if start.y >= self.rows.len() {
+            return String::new();
+        }
+        let end_y = end.y.min(self.rows.len() - 1);
+        if start.y == end_y {
+            return self
+                .rows
+                .get_mut(start.y)
+                .map(|row| row.remove(start.x..end.x))
+                .unwrap_or_default();
+        }
+        let tail = self.rows.get_mut(end_y).map(|row| row.split(end.x)).unwrap_or_default();
+        let removed_rows = self.rows.drain(start.y + 1..end_y + 1);
+        let Some(row) = self.rows.get_mut(start.y) else {
+            return String::new();
+        };
+        let first = row.split(start.x);
+        row.append(&tail);
+
+        let mut text = String::from_utf8_lossy(first.as_bytes()).into_owned();
+        for removed in &removed_rows {
+            text.push('\n');
+            text.push_str(&String::from_utf8_lossy(removed.as_bytes()));
         }
-        self.dirty = true;
-        if pos.x == self.rows[pos.y].len() && pos.y + 1 < len {
-            let next_row = self.rows.remove(pos.y + 1);
-            let row = &mut self.rows[pos.y];
-            row.append(&next_row);
+        text
+    }
+
+    fn apply(&mut self, change: &Change, reverse: bool) -> Position {
+        let insert = (change.kind == ChangeKind::Insert) != reverse;
+        if insert {
+            self.insert_text(&change.start, &change.text);
+        } else {
+            self.remove_text(&change.start, &change.end);
+            // Removing the text leaves the line the insert added empty.
+            if change.appended {
+                self.rows.remove(change.start.y);
+            }
+        }
+        if reverse {
+            change.cursor_before.clone()
         } else {
-            let row = &mut self.rows[pos.y];
-            row.delete(pos.x);
+            change.cursor_after.clone()
         }
     }
 
-    pub fn save(&mut self) -> Result<(), Error> {
-        if let Some(file_name) = &self.file_name {
-            let mut file = fs::File::create(file_name)?;
+    pub fn undo(&mut self) -> Option<Position> {
+        let mut cursor = None;
+        for change in self.history.undo() {
+            cursor = Some(self.apply(&change, true));
+        }
+        cursor
+    }
+
+    pub fn redo(&mut self) -> Option<Position> {
+        let mut cursor = None;
+        for change in self.history.redo() {
+            cursor = Some(self.apply(&change, false));
+        }
+        cursor
+    }
 
-            for row in &self.rows {
-                file.write_all(row.as_bytes())?;
-                file.write_all(b"\n")?;
+    pub fn save(&mut self) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "opened read-only because of an existing swap file",
+            ));
+        }
+        if self.directory.is_some() {
+            return Err(Error::new(ErrorKind::InvalidInput, "a directory listing cannot be saved"));
+        }
+        if let Some(file_name) = self.file_name.clone() {
+            if let Some(mut hex) = self.hex.take() {
+                let result = atomic::write(Path::new(&file_name), |file| file.write_all(hex.as_bytes()));
+                if result.is_ok() && hex.is_modified() {
+                    self.load_bytes(hex.as_bytes());
+                    hex.mark_saved();
+                }
+                self.hex = Some(hex);
+                result?;
+                // The text edited before hex mode was written out as well.
+                self.history.mark_saved();
+                self.unsaved = false;
+                return self.remove_swap();
             }
-            self.dirty = false;
+            let mut endings = (false, false);
+            atomic::write(Path::new(&file_name), |file| {
+                endings = self.write_contents(file, self.encoding)?;
+                Ok(())
+            })?;
+            self.file_type = FileType::from(&file_name);
+            self.unhighlight_rows(0);
+            self.mixed_endings = endings == (true, true);
+            self.unsaved = false;
+            self.history.mark_saved();
+            self.history.seal();
+            self.remove_swap()?;
         }
 
         Ok(())
     }
 
+    // Writes the text as it is saved, in `encoding`, and returns whether LF
+    // and CRLF line breaks were written.
+    fn write_contents(&self, file: &mut impl Write, encoding: Encoding) -> Result<(bool, bool), Error> {
+        let mut endings = (false, false);
+        let mut buffer = Vec::new();
+        if self.bom {
+            encoding.encode("\u{feff}", &mut buffer)?;
+        }
+        let last = self.rows.len().saturating_sub(1);
+        for (y, row) in self.rows.iter().enumerate() {
+            encoding.encode(row.text(0..row.len()), &mut buffer)?;
+            if y < last || self.final_newline {
+                let ending = row.ending();
+                encoding.encode(ending.as_str(), &mut buffer)?;
+                match ending {
+                    LineEnding::Lf => endings.0 = true,
+                    LineEnding::Crlf => endings.1 = true,
+                }
+            }
+            file.write_all(&buffer)?;
+            buffer.clear();
+        }
+        file.write_all(&buffer)?;
+        Ok(endings)
+    }
+
     pub fn is_dirty(&self) -> bool {
-        self.dirty
+        !self.history.is_saved() || self.unsaved || self.hex.as_ref().is_some_and(HexBytes::is_modified)
     }
 
-    pub fn find(&self, query: &str, after: &Position) -> Option<Position> {
+    // Returns the position and length in graphemes of the next match.
+    pub fn find(&self, query: &Query, after: &Position) -> Option<(Position, usize)> {
         let mut x = after.x;
-        for (y, row) in self.rows.iter().enumerate().skip(after.y) {
-            if let Some(x) = row.find(query, x) {
-                return Some(Position { x, y });
+        for (y, row) in (after.y..).zip(self.rows.iter_from(after.y)) {
+            if let Some((x, len)) = row.find(query, x) {
+                return Some((Position { x, y }, len));
             }
             x = 0;
         }
         None
     }
-}
\ No newline at end of file
+
+    // Returns the position and length of the closest match starting before
+    // `before`; positions past the last row search from the end of the document.
+    pub fn find_backward(&self, query: &Query, before: &Position) -> Option<(Position, usize)> {
+        let mut x = before.x;
+        let mut y = before.y;
+        if y >= self.rows.len() {
+            y = self.rows.len().checked_sub(1)?;
+            x = usize::MAX;
+        }
+        loop {
+            if let Some((x, len)) = self.rows.get(y).and_then(|row| row.find_backward(query, x)) {
+                return Some((Position { x, y }, len));
+            }
+            y = y.checked_sub(1)?;
+            x = usize::MAX;
+        }
+    }
+
+    // Counts the matches before each row, followed by the total.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn count_matches(&self, query: &Query) -> Vec<usize> {
+        let mut counts = Vec::with_capacity(self.rows.len() + 1);
+        let mut total = 0;
+        counts.push(total);
+        for row in self.rows.iter() {
+            total += row.matches(query).count();
+            counts.push(total);
+        }
+        counts
+    }
+
+    // The 1-based index of the match at `current`, given the counts from
+    // `count_matches`.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn match_index(&self, query: &Query, counts: &[usize], current: &Position) -> usize {
+        let before = counts.get(current.y).copied().unwrap_or(0);
+        let in_row = self.rows.get(current.y).map_or(0, |row| {
+            row.matches(query).take_while(|&(start, _)| start <= current.x).count()
+        });
+        before + in_row
+    }
+
+    // Changes whenever the text does.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn mark_edited(&mut self, y: usize) {
+        self.edited = Some(self.edited.map_or(y, |edited| edited.min(y)));
+    }
+
+    // Returns the first row changed since the last call, if any.
+    pub fn take_edited(&mut self) -> Option<usize> {
+        self.edited.take()
+    }
+
+    fn unhighlight_rows(&mut self, start: usize) {
+        self.highlighted = self.highlighted.min(start);
+    }
+
+    // Highlights every row up to `until`, resuming from the first row that was
+    // edited since the last call.
+    pub fn highlight(&mut self, until: usize) {
+        let until = until.min(self.rows.len());
+        let mut open = self
+            .highlighted
+            .checked_sub(1)
+            .and_then(|index| self.rows.get(index))
+            .and_then(Row::open_highlight);
+        for index in self.highlighted..until {
+            if let Some(row) = self.rows.get_mut(index) {
+                open = row.highlight(self.file_type.highlighting_options(), open);
+            }
+        }
+        self.highlighted = self.highlighted.max(until);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+
+    fn document(text: &str) -> Document {
+        let mut document = Document::default();
+        document.load(text);
+        document
+    }
+
+    fn contents(document: &Document) -> String {
+        let rows: Vec<&str> = (0..document.len())
+            .filter_map(|y| document.row(y))
+            .map(|row| row.text(0..row.len()))
+            .collect();
+        rows.join("\n")
+    }
+
+    // Typing each character at `x` on the first line, after the ones before
+    // it, then undoing and redoing all of it.
+    fn assert_undone(original: &str, x: usize, typed: &[char], expected: &str) {
+        let mut document = document(original);
+        let mut pos = Position { x, y: 0 };
+        for &c in typed {
+            let row = document.row(0).map_or(0, Row::len);
+            document.insert(&pos, c);
+            let grown = document.row(0).map_or(0, Row::len) - row;
+            pos.x += grown;
+        }
+        assert_eq!(contents(&document), expected);
+        assert!(document.is_dirty());
+
+        while document.undo().is_some() {}
+        assert_eq!(contents(&document), original);
+        assert!(!document.is_dirty());
+
+        while document.redo().is_some() {}
+        assert_eq!(contents(&document), expected);
+        assert!(document.is_dirty());
+    }
+
+    #[test]
+    fn undo_removes_marks_combined_with_their_neighbours() {
+        // A combining mark joins the letter before it.
+        assert_undone("ab\ncd", 1, &['\u{301}'], "a\u{301}b\ncd");
+        // A letter joins the lone mark after it.
+        assert_undone("\u{301}b", 0, &['e'], "e\u{301}b");
+        // A zero-width joiner and the emoji after it join the emoji before.
+        assert_undone("\u{1f468}!", 1, &['\u{200d}', '\u{1f469}'], "\u{1f468}\u{200d}\u{1f469}!");
+        // A skin tone modifier joins the emoji before it.
+        assert_undone("\u{1f44d}", 1, &['\u{1f3fd}'], "\u{1f44d}\u{1f3fd}");
+        // Plain text is undone as before.
+        assert_undone("ab", 1, &['x', 'y'], "axyb");
+    }
+
+    // Reads `bytes` as a file would be and writes them back as on save.
+    fn round_trip(bytes: &[u8]) -> (Vec<u8>, Document) {
+        let mut document = Document::default();
+        document.load_bytes(bytes);
+        let mut written = Vec::new();
+        document.write_contents(&mut written, document.encoding).unwrap();
+        (written, document)
+    }
+
+    #[test]
+    fn saving_keeps_the_encoding_bom_and_line_endings() {
+        let files: [&[u8]; 9] = [
+            b"",
+            b"one\ntwo\n",
+            b"one\ntwo",
+            b"one\r\ntwo\r\n",
+            b"one\r\ntwo\nthree",
+            b"\xef\xbb\xbfbom\r\n",
+            b"\xff\xfeu\x00\r\x00\n\x00",
+            b"\xfe\xff\x00u\x00\n",
+            b"caf\xe9 \x80\r\n",
+        ];
+        for bytes in files {
+            let (written, document) = round_trip(bytes);
+            assert_eq!(written, bytes, "{}", document.encoding());
+        }
+        let (_, document) = round_trip(b"caf\xe9\n");
+        assert_eq!(document.encoding(), "Windows-1252");
+        assert_eq!(contents(&document), "café");
+    }
+
+    #[test]
+    fn new_lines_take_the_usual_line_ending() {
+        let mut document = document("one\r\ntwo\r\n");
+        document.insert(&Position { x: 3, y: 0 }, '\n');
+        document.insert_str(&Position { x: 3, y: 2 }, "\nfour");
+        let mut written = Vec::new();
+        document.write_contents(&mut written, Encoding::Utf8).unwrap();
+        assert_eq!(written, b"one\r\n\r\ntwo\r\nfour\r\n");
+    }
+
+    #[test]
+    fn find_backward_searches_earlier_rows_from_their_end() {
+        let document = document("one two\nthree\ntwo two");
+        let query = Query::new("two", SearchOptions::default()).unwrap();
+        let found = |x, y| {
+            document
+                .find_backward(&query, &Position { x, y })
+                .map(|(position, len)| (position.x, position.y, len))
+        };
+        assert_eq!(found(4, 2), Some((0, 2, 3)));
+        assert_eq!(found(0, 2), Some((4, 0, 3)));
+        assert_eq!(found(4, 0), None);
+        // Past the last row, the search starts from the end of the document.
+        assert_eq!(found(0, 3), Some((4, 2, 3)));
+        let forward = document.find(&query, &Position { x: 5, y: 0 });
+        assert_eq!(forward.map(|(position, _)| (position.x, position.y)), Some((0, 2)));
+        assert!(document.find(&query, &Position { x: 5, y: 2 }).is_none());
+    }
+
+    #[test]
+    fn matches_are_counted_without_overlap() {
+        let mut document = document("aaaa\nb\naaa");
+        let query = Query::new("aa", SearchOptions::default()).unwrap();
+        let counts = document.count_matches(&query);
+        assert_eq!(counts, [0, 2, 2, 3]);
+        let index = |document: &Document, x, y| document.match_index(&query, &counts, &Position { x, y });
+        assert_eq!(index(&document, 0, 0), 1);
+        assert_eq!(index(&document, 2, 0), 2);
+        assert_eq!(index(&document, 1, 2), 3);
+
+        let revision = document.revision();
+        document.insert(&Position { x: 0, y: 1 }, 'a');
+        assert_ne!(document.revision(), revision);
+    }
+
+    #[test]
+    fn undo_removes_a_combining_mark_inserted_with_a_line_break() {
+        let mut document = document("cd");
+        document.insert_str(&Position { x: 1, y: 0 }, "\u{301}\nx");
+        assert_eq!(contents(&document), "c\u{301}\nxd");
+        document.undo();
+        assert_eq!(contents(&document), "cd");
+        assert!(!document.is_dirty());
+    }
+}
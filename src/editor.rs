@@ -1,16 +1,41 @@
+use super::clipboard;
+use super::completion;
+use super::{Config, LineNumbers};
 use super::Terminal;
+use super::terminal::{Backend, Event};
+use super::directory;
 use super::Document;
-use super::Row;
+use super::Encoding;
+use super::finder;
+use super::frame::Frame;
+use super::hex::{self, HexBytes, BYTES_PER_LINE};
+use super::highlighting;
+use super::{LineEnding, Row};
+use super::Query;
+use super::SearchOptions;
+use super::window::{self, Layout, Rect, Window};
 
 use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use termion::event::Key;
+use termion::{color, style};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const QUIT_CONFIRM: u8 = 3;
+// Smallest window a split may leave, status bar included.
+const MIN_WINDOW_WIDTH: usize = 10;
+const MIN_WINDOW_HEIGHT: usize = 2;
+const DIRECTORY_HELP: &str = "Directory: Enter = open | - = up | c = create | r = rename | d = delete";
+// Files the finder lists at most.
+const MAX_MATCHES: usize = 1000;
 
 #[non_exhaustive]
-#[derive(Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -30,79 +55,345 @@ impl StatusMessage {
     }
 }
 
+#[derive(Default)]
+struct SearchState {
+    options: SearchOptions,
+    // Set while the search prompt is open so matches are highlighted.
+    query: Option<Query>,
+    current: Option<(Position, usize)>,
+    counter: (usize, usize),
+    counts: Option<MatchCounts>,
+    wrapped: bool,
+}
+
+// The matches before each row for the search prompt, kept until the query,
+// its options or the text change.
+struct MatchCounts {
+    text: String,
+    options: SearchOptions,
+    revision: u64,
+    counts: Vec<usize>,
+}
+
+// An open document that is not being shown, along with where the cursor was
+// when it was left.
+struct Buffer {
+    document: Document,
+    cursor_position: Position,
+    offset: Position,
+    text_position: Position,
+}
+
+impl Buffer {
+    fn new(document: Document) -> Self {
+        Self {
+            document,
+            cursor_position: Position::default(),
+            offset: Position::default(),
+            text_position: Position::default(),
+        }
+    }
+}
+
+// A window as it is drawn: the buffer it shows, its cursor and offset, and
+// the size of its text area.
+struct View<'a> {
+    document: &'a Document,
+    buffer: usize,
+    cursor_position: &'a Position,
+    offset: &'a Position,
+    width: usize,
+    height: usize,
+    active: bool,
+}
+
+// A list shown in place of the text, either while the user picks one of its
+// items or just for reference.
+struct Picker {
+    items: Vec<String>,
+    selected: Option<usize>,
+    offset: usize,
+    // Shown beside the list, if any.
+    preview: Option<Vec<Row>>,
+    // The text the items were filtered by, if any.
+    query: String,
+}
+
+impl Picker {
+    fn new(items: Vec<String>) -> Self {
+        Self {
+            items,
+            selected: None,
+            offset: 0,
+            preview: None,
+            query: String::new(),
+        }
+    }
+
+    // Selects the item at `index`, or the last one, scrolling the list so that
+    // it stays within `height` lines.
+    fn select(&mut self, index: usize, height: usize) {
+        let index = index.min(self.items.len().saturating_sub(1));
+        self.selected = Some(index);
+        if index < self.offset {
+            self.offset = index;
+        } else if index >= self.offset.saturating_add(height) {
+            self.offset = index.saturating_add(1).saturating_sub(height);
+        }
+    }
+
+    // Moves the selection for the arrow keys, Page Up/Down, Home and End, and
+    // returns whether `key` was one of them.
+    fn navigate(&mut self, key: Key, height: usize) -> bool {
+        let selected = self.selected.unwrap_or(0);
+        let index = match key {
+            Key::Up => selected.saturating_sub(1),
+            Key::Down => selected.saturating_add(1),
+            Key::PageUp => selected.saturating_sub(height),
+            Key::PageDown => selected.saturating_add(height),
+            Key::Home => 0,
+            Key::End => self.items.len(),
+            _ => return false,
+        };
+        self.select(index, height);
+        true
+    }
+}
+
 pub struct Editor {
     should_quit: bool,
-    terminal: Terminal,
+    terminal: Box<dyn Backend>,
+    frame: Frame,
     cursor_position: Position,
     document: Document,
     offset: Position,
+    // The other open buffers, in order, with the shown one left out.
+    buffers: Vec<Buffer>,
+    // Index of the shown buffer among all of them.
+    current: usize,
+    // Every window, the current one included, though its cursor and offset are
+    // kept up to date in the fields above instead.
+    windows: Vec<Window>,
+    window: usize,
+    layout: Layout,
+    picker: Option<Picker>,
+    // The files found by the last walk for the file finder, kept until the
+    // editor creates, renames or deletes files.
+    files: Option<Vec<finder::Candidate>>,
     status_message: StatusMessage,
     quit_confirm: u8,
+    search_state: SearchState,
+    selection_anchor: Option<Position>,
+    clipboard: String,
+    line_numbers: LineNumbers,
+    // Where the cursor was in the text before switching to hex.
+    text_position: Position,
+    swap_written: Instant,
+    config: Config,
 }
 
 impl Editor {
     pub fn run(&mut self) {
+        for index in 0..self.buffer_count() {
+            self.switch_buffer(index);
+            if self.document.has_swap() {
+                if let Err(error) = self.resolve_swap() {
+                    self.die(&error);
+                }
+            }
+        }
+        self.switch_buffer(0);
         loop {
             if let Err(error) = self.refresh_screen() {
-                die(error);
+                self.die(&error);
             }
             if self.should_quit {
+                let _ = self.document.remove_swap();
+                for buffer in &mut self.buffers {
+                    let _ = buffer.document.remove_swap();
+                }
                 break;
             }
-            if let Err(error) = self.process_keypress() {
-                die(error);
+            match self.process_keypress() {
+                // The input is gone, e.g. a scripted backend ran out of keys.
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+                Err(error) => self.die(&error),
+                Ok(()) => (),
             }
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
-        let args: Vec<String> = env::args().collect();
-        let mut initial_status = String::from("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit");
-        let document = if let Some(file_name) = args.get(1) {
-            let doc = Document::open(file_name);
-            if let Ok(doc) = doc {
-                doc
-            } else {
-                initial_status = format!("ERROR! Could not open file: {}", file_name);
-                Document::default()
+        let args: Vec<String> = env::args().skip(1).collect();
+        let file_names: Vec<&str> = args.iter().map(String::as_str).collect();
+        Self::new(
+            Box::new(Terminal::default().expect("Failed to initialize terminal")),
+            Config::from_env(),
+            &file_names,
+        )
+    }
+
+    // Opens each of `file_names` in its own buffer and shows the first.
+    #[must_use]
+    pub fn new(terminal: Box<dyn Backend>, config: Config, file_names: &[&str]) -> Self {
+        let mut initial_status = String::from("HELP: Ctrl-F = find | Ctrl-R = replace | Ctrl-S = save | Ctrl-Z/Ctrl-Y = undo/redo | Ctrl-O = open | Ctrl-B = buffers | Ctrl-Q = quit");
+        let mut documents = Vec::new();
+        for file_name in file_names {
+            match Document::open(file_name) {
+                Ok(doc) => {
+                    if doc.is_binary() {
+                        initial_status = format!("WARNING! {} is binary, shown one character per byte. Ctrl-T = hex mode.", file_name);
+                    } else if doc.directory().is_some() {
+                        initial_status = DIRECTORY_HELP.to_owned();
+                    }
+                    documents.push(doc);
+                },
+                Err(error) => {
+                    initial_status = format!("ERROR! Could not open file {}: {}", file_name, error);
+                },
             }
-        } else {
-            Document::default()
-        };
+        }
+        let mut documents = documents.into_iter();
+        let document = documents.next().unwrap_or_default();
 
         Self {
             should_quit: false,
-            terminal: Terminal::default().expect("Failed to initialize terminal"),
+            terminal,
+            frame: Frame::default(),
             cursor_position: Position::default(),
             document,
             offset: Position::default(),
+            buffers: documents.map(Buffer::new).collect(),
+            current: 0,
+            windows: vec![Window::default()],
+            window: 0,
+            layout: Layout::Window(0),
+            picker: None,
+            files: None,
             status_message: StatusMessage::from(initial_status),
             quit_confirm: QUIT_CONFIRM,
+            search_state: SearchState::default(),
+            selection_anchor: None,
+            clipboard: String::new(),
+            line_numbers: config.line_numbers,
+            text_position: Position::default(),
+            swap_written: Instant::now(),
+            config,
         }
     }
 
-    fn refresh_screen(&self) -> Result<(), std::io::Error> {
-        Terminal::cursor_hide();
-        Terminal::cursor_position(&Position::default());
+    fn refresh_screen(&mut self) -> Result<(), std::io::Error> {
         if self.should_quit {
-            Terminal::clear_screen();
-            println!("Goodbye.\r");
+            self.terminal.cursor_hide();
+            self.terminal.clear_screen();
+            self.terminal.cursor_position(&Position::default());
+            self.terminal.write("Goodbye.\r\n");
+            self.terminal.cursor_show();
+            self.frame.invalidate();
         } else {
-            self.draw_rows();
-            self.draw_status_bar();
-            self.draw_message_bar();
-            Terminal::cursor_position(&Position {
-                x: self.cursor_position.x.saturating_sub(self.offset.x),
-                y: self.cursor_position.y.saturating_sub(self.offset.y),
-            });
+            let (areas, mut separators) = self.arrange();
+            for (window, area) in &areas {
+                let (buffer, top) = if *window == self.window {
+                    (self.current, self.offset.y)
+                } else {
+                    self.windows.get(*window).map_or((self.current, 0), |window| (window.buffer, window.offset.y))
+                };
+                if let Some(document) = self.document_at_mut(buffer) {
+                    document.highlight(top.saturating_add(area.height));
+                }
+            }
+            let mut pieces = Vec::with_capacity(areas.len());
+            let mut cursor = Position::default();
+            let mut picker_separators = Vec::new();
+            for (window, area) in areas {
+                let Some(view) = self.view(window, area) else {
+                    continue;
+                };
+                if let Some(picker) = &self.picker {
+                    if view.active {
+                        cursor.y = picker.selected.unwrap_or(0).saturating_sub(picker.offset);
+                        let screen = Rect {
+                            x: 0,
+                            y: 0,
+                            width: usize::from(self.terminal.size().width),
+                            height: usize::from(self.terminal.size().height),
+                        };
+                        let status = self.draw_status_bar(&View {
+                            width: screen.width,
+                            ..view
+                        });
+                        pieces = vec![(
+                            Rect {
+                                y: screen.height,
+                                height: 1,
+                                ..screen
+                            },
+                            vec![status],
+                        )];
+                        if let Some(rows) = &picker.preview {
+                            // The list on the left, the preview on the right.
+                            #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+                            let list = Rect {
+                                width: screen.width.saturating_sub(1) / 2,
+                                ..screen
+                            };
+                            let separator = Rect { x: list.width, width: 1, ..screen };
+                            let preview = Rect {
+                                x: list.width.saturating_add(1),
+                                width: screen.width.saturating_sub(list.width).saturating_sub(1),
+                                ..screen
+                            };
+                            pieces.push((list, self.draw_picker(picker, &list)));
+                            pieces.push((preview, self.draw_preview(rows, &preview)));
+                            picker_separators.push(separator);
+                        } else {
+                            pieces.push((screen, self.draw_picker(picker, &screen)));
+                        }
+                    }
+                    continue;
+                }
+                if view.active {
+                    cursor = if self.document.hex().is_some() {
+                        Position {
+                            x: hex::column(self.cursor_position.x),
+                            y: self.cursor_position.y.saturating_sub(self.offset.y),
+                        }
+                    } else {
+                        Position {
+                            x: self
+                                .cursor_column()
+                                .saturating_sub(self.offset.x)
+                                .saturating_add(self.gutter_width(&self.document)),
+                            y: self.cursor_position.y.saturating_sub(self.offset.y),
+                        }
+                    };
+                    cursor.x = cursor.x.saturating_add(area.x);
+                    cursor.y = cursor.y.saturating_add(area.y);
+                }
+                let mut lines = self.draw_rows(&view);
+                lines.push(self.draw_status_bar(&view));
+                pieces.push((area, lines));
+            }
+            if self.picker.is_some() {
+                separators = picker_separators;
+            }
+            let height = usize::from(self.terminal.size().height).saturating_add(1);
+            let mut lines = window::compose(height, pieces, &separators);
+            lines.push(self.draw_message_bar());
+            // The whole frame goes out in a single write.
+            let output = self.frame.render(lines, &cursor);
+            self.terminal.write(&output);
         }
-        Terminal::cursor_show();
-        Terminal::flush()
+        self.terminal.flush()
     }
 
     fn save(&mut self) {
         if self.document.file_name.is_none() {
-            let new_name = self.prompt("Save as: ", |_, _, _| {}).unwrap_or(None);
+            let new_name = self
+                .prompt(|_| "Save as: ".to_owned(), |_, _, _| {})
+                .unwrap_or(None)
+                .filter(|name| !name.is_empty());
             if new_name.is_none() {
                 self.status_message = StatusMessage::from("Save aborted.".to_owned());
                 return;
@@ -110,38 +401,150 @@ impl Editor {
             self.document.file_name = new_name;
         }
 
-        if self.document.save().is_ok() {
-            self.status_message = StatusMessage::from("File saved successfully.".to_owned());
+        if self.document.file_name.as_ref().is_some_and(|name| !Path::new(name).exists()) {
+            self.files = None;
+        }
+        self.status_message = match self.document.save() {
+            Ok(()) => StatusMessage::from("File saved successfully.".to_owned()),
+            Err(error) => StatusMessage::from(format!("Error writing file: {}", error)),
+        };
+    }
+
+    fn search_flags(&self) -> String {
+        let flags = self.search_state.options.describe();
+        if flags.is_empty() {
+            String::new()
         } else {
-            self.status_message = StatusMessage::from("Error writing file!".to_owned());
+            format!(" [{}]", flags)
         }
     }
 
+    fn search_prompt(&self) -> String {
+        let search = &self.search_state;
+        let flags = self.search_flags();
+        let wrapped = if search.wrapped {
+            "Search wrapped | "
+        } else {
+            ""
+        };
+        let counter = match search.counter {
+            _ if search.query.is_none() => String::new(),
+            (_, 0) => " (no matches)".to_owned(),
+            (index, total) => format!(" (match {} of {})", index, total),
+        };
+        format!(
+            "{}Search{}{} (ESC to cancel, Arrows to navigate, Alt-R/C/W = regex/case/word): ",
+            wrapped, flags, counter
+        )
+    }
+
+    // The 1-based index of the match at `position` and the number of matches,
+    // counting them again only when the query, its options or the text changed.
+    fn count_matches(&mut self, text: &str, query: &Query, position: &Position) -> (usize, usize) {
+        let options = self.search_state.options;
+        let revision = self.document.revision();
+        let current = self.search_state.counts.as_ref().is_some_and(|counts| {
+            counts.text == text && counts.options == options && counts.revision == revision
+        });
+        if !current {
+            self.search_state.counts = Some(MatchCounts {
+                text: text.to_owned(),
+                options,
+                revision,
+                counts: self.document.count_matches(query),
+            });
+        }
+        let counts = self.search_state.counts.as_ref().map_or(&[][..], |counts| &counts.counts);
+        let total = counts.last().copied().unwrap_or(0);
+        (self.document.match_index(query, counts, position), total)
+    }
+
+    // Finds the next match in the given direction, wrapping around at either end
+    // of the document. Searching forward starts `skip` graphemes after the
+    // cursor, to move past a match under it.
+    fn find_match(&mut self, query: &Query, forward: bool, skip: usize) -> Option<(Position, usize)> {
+        let Position { x, y } = self.cursor_position;
+        let found = if forward {
+            self.document.find(query, &Position { x: x.saturating_add(skip), y })
+        } else {
+            self.document.find_backward(query, &self.cursor_position)
+        };
+        if found.is_some() {
+            return found;
+        }
+        let found = if forward {
+            self.document.find(query, &Position::default())
+        } else {
+            self.document.find_backward(query, &Position { x: 0, y: self.document.len() })
+        };
+        self.search_state.wrapped = found.is_some();
+        found
+    }
+
     fn search(&mut self) {
         let old_position = self.cursor_position.clone();
-        if let Some(query) = self.prompt(
-            "Search (ESC to cancel, Arrows to navigate): ",
+        self.search_state.wrapped = false;
+        self.search_state.counts = None;
+        let query = self.prompt(
+            Self::search_prompt,
             |editor, key, query| {
-                let mut moved = false;
+                let mut forward = true;
+                let mut skip = 0;
                 match key {
+                    // Move past the whole match, as the highlighting and the
+                    // counter do.
                     Key::Right | Key::Down => {
-                        editor.move_cursor(Key::Right);
-                        moved = true;
+                        skip = editor.search_state.current.as_ref().map_or(1, |(_, len)| (*len).max(1));
+                    },
+                    Key::Left | Key::Up => forward = false,
+                    Key::Alt(flag) => {
+                        let options = &mut editor.search_state.options;
+                        match flag {
+                            'r' => options.regex = !options.regex,
+                            'c' => options.ignore_case = !options.ignore_case,
+                            'w' => options.whole_word = !options.whole_word,
+                            _ => (),
+                        }
                     },
                     _ => (),
                 }
-                if let Some(position) = editor.document.find(&query, &editor.cursor_position) {
-                    editor.cursor_position = position;
-                    editor.scroll();
-                } else if moved {
-                    editor.move_cursor(Key::Left);
+                editor.search_state.wrapped = false;
+                editor.search_state.current = None;
+                editor.search_state.query = None;
+                if query.is_empty() {
+                    return;
+                }
+                if let Ok(compiled) = Query::new(query, editor.search_state.options) {
+                    if let Some((position, len)) = editor.find_match(&compiled, forward, skip) {
+                        editor.search_state.counter = editor.count_matches(query, &compiled, &position);
+                        editor.cursor_position = position.clone();
+                        editor.search_state.current = Some((position, len));
+                        editor.scroll();
+                    } else {
+                        editor.search_state.counter = (0, 0);
+                    }
+                    editor.search_state.query = Some(compiled);
                 }
             }
-        ).unwrap_or(None) {
-            if let Some(position) = self.document.find(&query[..], &old_position) {
-                self.cursor_position = position;
-            } else {
-                self.status_message = StatusMessage::from(format!("Not found: {}", query));
+        ).unwrap_or(None).filter(|query| !query.is_empty());
+        self.search_state.query = None;
+        self.search_state.current = None;
+
+        if let Some(query) = query {
+            match Query::new(&query, self.search_state.options) {
+                Ok(compiled) => {
+                    if let Some((position, _)) = self.document.find(&compiled, &self.cursor_position) {
+                        self.cursor_position = position;
+                        if self.search_state.wrapped {
+                            self.status_message = StatusMessage::from("Search wrapped.".to_owned());
+                        }
+                    } else {
+                        self.status_message = StatusMessage::from(format!("Not found: {}", query));
+                    }
+                },
+                Err(_) => {
+                    self.status_message = StatusMessage::from(format!("Invalid regex: {}", query));
+                },
             }
         } else {
             self.cursor_position = old_position;
@@ -149,31 +552,1050 @@ impl Editor {
         }
     }
 
-    #[allow(clippy::integer_arithmetic)]
+    #[allow(clippy::arithmetic_side_effects)]
+    fn replace(&mut self) -> Result<(), std::io::Error> {
+        let Some(query) = self
+            .prompt(|editor| format!("Replace{}: ", editor.search_flags()), |_, _, _| {})?
+            .filter(|query| !query.is_empty())
+        else {
+            self.status_message = StatusMessage::from("Replace aborted.".to_owned());
+            return Ok(());
+        };
+        let Ok(compiled) = Query::new(&query, self.search_state.options) else {
+            self.status_message = StatusMessage::from(format!("Invalid regex: {}", query));
+            return Ok(());
+        };
+        let Some(replacement) = self.prompt(|_| format!("Replace {} with: ", query), |_, _, _| {})? else {
+            self.status_message = StatusMessage::from("Replace aborted.".to_owned());
+            return Ok(());
+        };
+
+        let mut position = Position::default();
+        let mut last_match = None;
+        let mut replaced = 0;
+        let mut changed = false;
+        let mut replace_all = false;
+        self.search_state.query = Some(compiled.clone());
+        while let Some((found, len)) = self.document.find(&compiled, &position) {
+            last_match = Some(found.clone());
+            if !replace_all {
+                self.cursor_position = found.clone();
+                self.scroll();
+                self.search_state.current = Some((found.clone(), len));
+                self.status_message = StatusMessage::from(
+                    "Replace this occurrence? (y)es, (n)o, (a)ll, (q)uit".to_owned(),
+                );
+                self.refresh_screen()?;
+                match self.read_key()? {
+                    Key::Char('y') => (),
+                    Key::Char('a') => replace_all = true,
+                    Key::Char('n') => {
+                        position = Position { x: found.x + len.max(1), y: found.y };
+                        continue;
+                    },
+                    Key::Char('q') | Key::Esc => break,
+                    _ => continue,
+                }
+            }
+            // All the replacements are undone together; replacing nothing with
+            // nothing changes nothing and is not recorded.
+            let end = if len == 0 && replacement.is_empty() {
+                found
+            } else {
+                let end = self.document.replace(&found, len, &replacement, changed);
+                changed = true;
+                end
+            };
+            replaced += 1;
+            // Step past empty matches so they are not replaced forever.
+            position = if len == 0 {
+                Position { x: end.x + 1, y: end.y }
+            } else {
+                end
+            };
+        }
+        self.search_state.query = None;
+        self.search_state.current = None;
+
+        if let Some(position) = last_match {
+            self.cursor_position = position;
+        }
+        self.status_message = StatusMessage::from(format!(
+            "Replaced {} occurrence{}.",
+            replaced,
+            if replaced == 1 { "" } else { "s" }
+        ));
+        Ok(())
+    }
+
+    // Asks what to do with the swap file another session left behind or is
+    // still writing.
+    fn resolve_swap(&mut self) -> Result<(), std::io::Error> {
+        let question = match self.document.swap_owner() {
+            Some(pid) => format!(
+                "The file is being edited in another session (pid {}). Open (o)read-only, (r)ecover or (d)iscard?",
+                pid
+            ),
+            None => "Found a swap file with unsaved changes. (r)ecover, (d)iscard or open (o)read-only?".to_owned(),
+        };
+        loop {
+            self.status_message = StatusMessage::from(question.clone());
+            self.refresh_screen()?;
+            let result = match self.read_key()? {
+                Key::Char('r') => self.document.recover().map(|()| "Recovered unsaved changes."),
+                Key::Char('d') => self.document.discard_swap().map(|()| "Swap file discarded."),
+                Key::Char('o') | Key::Esc => {
+                    self.document.set_read_only();
+                    Ok("Opened read-only.")
+                },
+                _ => continue,
+            };
+            self.status_message = match result {
+                Ok(message) => StatusMessage::from(message.to_owned()),
+                Err(error) => {
+                    self.document.set_read_only();
+                    StatusMessage::from(format!("Could not use swap file, opened read-only: {}", error))
+                },
+            };
+            return Ok(());
+        }
+    }
+
+    // Writes unsaved changes to the swap file, at most once per interval.
+    fn update_swap(&mut self) -> Result<(), std::io::Error> {
+        if self.swap_written.elapsed() < self.config.swap_interval {
+            return Ok(());
+        }
+        self.swap_written = Instant::now();
+        let mut result = self.document.update_swap();
+        for buffer in &mut self.buffers {
+            result = result.and(buffer.document.update_swap());
+        }
+        if let Err(error) = result {
+            self.status_message = StatusMessage::from(format!("Could not write swap file: {}", error));
+            self.refresh_screen()?;
+        }
+        Ok(())
+    }
+
+    fn buffer_count(&self) -> usize {
+        self.buffers.len().saturating_add(1)
+    }
+
+    fn document_at(&self, buffer: usize) -> Option<&Document> {
+        match buffer.checked_sub(self.current) {
+            None => self.buffers.get(buffer).map(|buffer| &buffer.document),
+            Some(0) => Some(&self.document),
+            Some(_) => self.buffers.get(buffer.saturating_sub(1)).map(|buffer| &buffer.document),
+        }
+    }
+
+    fn document_at_mut(&mut self, buffer: usize) -> Option<&mut Document> {
+        match buffer.checked_sub(self.current) {
+            None => self.buffers.get_mut(buffer).map(|buffer| &mut buffer.document),
+            Some(0) => Some(&mut self.document),
+            Some(_) => self.buffers.get_mut(buffer.saturating_sub(1)).map(|buffer| &mut buffer.document),
+        }
+    }
+
+    // The area of each window, in order from the top left, and of the
+    // separators between windows side by side. The message bar is left out.
+    fn arrange(&self) -> (Vec<(usize, Rect)>, Vec<Rect>) {
+        let size = self.terminal.size();
+        let screen = Rect {
+            x: 0,
+            y: 0,
+            width: usize::from(size.width),
+            height: usize::from(size.height).saturating_add(1),
+        };
+        let mut areas = Vec::with_capacity(self.windows.len());
+        let mut separators = Vec::new();
+        self.layout.arrange(screen, &mut areas, &mut separators);
+        (areas, separators)
+    }
+
+    // The area of the current window, status bar included.
+    fn area(&self) -> Rect {
+        self.arrange()
+            .0
+            .into_iter()
+            .find_map(|(window, area)| (window == self.window).then_some(area))
+            .unwrap_or_default()
+    }
+
+    fn view(&self, window: usize, area: Rect) -> Option<View<'_>> {
+        let active = window == self.window;
+        let (buffer, cursor_position, offset) = if active {
+            (self.current, &self.cursor_position, &self.offset)
+        } else {
+            let window = self.windows.get(window)?;
+            (window.buffer, &window.cursor_position, &window.offset)
+        };
+        Some(View {
+            document: self.document_at(buffer)?,
+            buffer,
+            cursor_position,
+            offset,
+            width: area.width,
+            height: area.height.saturating_sub(1),
+            active,
+        })
+    }
+
+    // Splits the current window in two showing the same buffer and moves to
+    // the new half, below or to the right.
+    fn split_window(&mut self, side_by_side: bool) {
+        let area = self.area();
+        let fits = if side_by_side {
+            area.width >= MIN_WINDOW_WIDTH.saturating_mul(2).saturating_add(1)
+        } else {
+            area.height >= MIN_WINDOW_HEIGHT.saturating_mul(2)
+        };
+        if !fits {
+            self.status_message = StatusMessage::from("Not enough room to split the window.".to_owned());
+            return;
+        }
+        let new = self.windows.len();
+        self.windows.push(Window::default());
+        self.layout.split(self.window, new, side_by_side);
+        self.store_window();
+        self.window = new;
+        self.frame.invalidate();
+        self.scroll();
+    }
+
+    // Closes the current window, leaving its buffer open, and moves to the
+    // window before it.
+    fn close_window(&mut self) {
+        if self.windows.len() == 1 {
+            self.status_message = StatusMessage::from("Cannot close the last window.".to_owned());
+            return;
+        }
+        let order = self.window_order();
+        let position = order.iter().position(|&window| window == self.window).unwrap_or(0);
+        let next = if position == 0 {
+            order.get(1)
+        } else {
+            order.get(position.saturating_sub(1))
+        };
+        let Some(&next) = next else {
+            return;
+        };
+        let closed = self.window;
+        self.windows.remove(closed);
+        self.layout.remove(closed);
+        self.load_window(if next > closed { next.saturating_sub(1) } else { next });
+        self.frame.invalidate();
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn cycle_window(&mut self) {
+        let order = self.window_order();
+        let position = order.iter().position(|&window| window == self.window).unwrap_or(0);
+        if let Some(&next) = order.get((position + 1) % order.len().max(1)) {
+            self.store_window();
+            self.load_window(next);
+        }
+    }
+
+    fn window_order(&self) -> Vec<usize> {
+        self.arrange().0.into_iter().map(|(window, _)| window).collect()
+    }
+
+    // Keeps the state of the current window in `windows` before leaving it.
+    fn store_window(&mut self) {
+        if let Some(window) = self.windows.get_mut(self.window) {
+            *window = Window {
+                buffer: self.current,
+                cursor_position: self.cursor_position.clone(),
+                offset: self.offset.clone(),
+                text_position: self.text_position.clone(),
+            };
+        }
+    }
+
+    fn load_window(&mut self, index: usize) {
+        let Some(window) = self.windows.get(index).cloned() else {
+            return;
+        };
+        self.window = index;
+        self.switch_buffer(window.buffer);
+        self.cursor_position = window.cursor_position;
+        self.offset = window.offset;
+        self.text_position = window.text_position;
+        self.selection_anchor = None;
+        // Another window may have shortened the document in the meantime.
+        if let Some(hex) = self.document.hex() {
+            #[allow(clippy::arithmetic_side_effects)]
+            let line = BYTES_PER_LINE * 2;
+            let last = hex.len().saturating_mul(2).saturating_sub(1);
+            #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+            {
+                let nibble = (self.cursor_position.y * line + self.cursor_position.x).min(last);
+                self.cursor_position = Position {
+                    x: nibble % line,
+                    y: nibble / line,
+                };
+            }
+        } else {
+            self.cursor_position.y = self.cursor_position.y.min(self.document.len());
+            self.move_cursor(Key::Null);
+        }
+        self.scroll();
+    }
+
+    fn window_command(&mut self) -> Result<(), std::io::Error> {
+        self.status_message = StatusMessage::from(
+            "Window: (s)plit horizontally, split (v)ertically, (w) next window, (q) close".to_owned(),
+        );
+        self.refresh_screen()?;
+        let key = self.read_key()?;
+        self.status_message = StatusMessage::from(String::new());
+        match key {
+            Key::Char('s') => self.split_window(false),
+            Key::Char('v') => self.split_window(true),
+            Key::Char('w') | Key::Ctrl('w') => self.cycle_window(),
+            Key::Char('q' | 'c') => self.close_window(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // Shows the buffer at `index`, keeping the cursor of the one shown so far.
+    fn switch_buffer(&mut self, index: usize) {
+        if index == self.current || index >= self.buffer_count() {
+            return;
+        }
+        let shown = Buffer {
+            document: mem::take(&mut self.document),
+            cursor_position: mem::take(&mut self.cursor_position),
+            offset: mem::take(&mut self.offset),
+            text_position: mem::take(&mut self.text_position),
+        };
+        self.buffers.insert(self.current, shown);
+        let buffer = self.buffers.remove(index);
+        self.show_buffer(buffer);
+        self.current = index;
+    }
+
+    fn show_buffer(&mut self, buffer: Buffer) {
+        self.document = buffer.document;
+        self.cursor_position = buffer.cursor_position;
+        self.offset = buffer.offset;
+        self.text_position = buffer.text_position;
+        self.selection_anchor = None;
+        self.frame.invalidate();
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn cycle_buffer(&mut self, forward: bool) {
+        let count = self.buffer_count();
+        if count == 1 {
+            self.status_message = StatusMessage::from("No other buffers.".to_owned());
+            return;
+        }
+        let index = if forward {
+            (self.current + 1) % count
+        } else {
+            (self.current + count - 1) % count
+        };
+        self.switch_buffer(index);
+        self.status_message = StatusMessage::from(self.describe_buffer());
+    }
+
+    // The index of the buffer that has `file_name` open, if any.
+    fn find_buffer(&self, file_name: &str) -> Option<usize> {
+        let path = fs::canonicalize(file_name).ok()?;
+        let is_open = |document: &Document| {
+            document
+                .file_name
+                .as_ref()
+                .and_then(|name| fs::canonicalize(name).ok())
+                .is_some_and(|name| name == path)
+        };
+        if is_open(&self.document) {
+            return Some(self.current);
+        }
+        let index = self.buffers.iter().position(|buffer| is_open(&buffer.document))?;
+        Some(if index < self.current { index } else { index.saturating_add(1) })
+    }
+
+    fn describe_buffer(&self) -> String {
+        let name = self.document.file_name.as_deref().unwrap_or("[Untitled]");
+        format!("Buffer {} of {}: {}", self.current.saturating_add(1), self.buffer_count(), name)
+    }
+
+    fn open_prompt(&mut self) -> Result<(), std::io::Error> {
+        let file_name = self.prompt(
+            |_| "Open (Tab to complete): ".to_owned(),
+            |editor, key, path| {
+                editor.picker = None;
+                if key == Key::Char('\t') {
+                    let (completed, names) = completion::complete(path);
+                    *path = completed;
+                    if names.len() > 1 {
+                        editor.picker = Some(Picker::new(names));
+                    }
+                }
+            },
+        );
+        self.picker = None;
+        let Some(file_name) = file_name?.filter(|name| !name.is_empty()) else {
+            self.status_message = StatusMessage::from("Open aborted.".to_owned());
+            return Ok(());
+        };
+        self.open(&completion::expand_home(&file_name))
+    }
+
+    // Shows `file_name` in a new buffer after the current one, or in the buffer
+    // that already has it open.
+    fn open(&mut self, file_name: &str) -> Result<(), std::io::Error> {
+        if let Some(index) = self.find_buffer(file_name) {
+            self.switch_buffer(index);
+            self.status_message = StatusMessage::from(self.describe_buffer());
+            return Ok(());
+        }
+        let document = match Document::open(file_name) {
+            Ok(document) => document,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERROR! Could not open file {}: {}", file_name, error));
+                return Ok(());
+            },
+        };
+        // Other buffers from `current` on follow the shown one.
+        self.buffers.insert(self.current, Buffer::new(document));
+        for window in &mut self.windows {
+            if window.buffer > self.current {
+                window.buffer = window.buffer.saturating_add(1);
+            }
+        }
+        self.switch_buffer(self.current.saturating_add(1));
+        if self.document.has_swap() {
+            self.resolve_swap()?;
+        } else if self.document.is_binary() {
+            self.status_message = StatusMessage::from(
+                "WARNING! Binary file, shown one character per byte. Ctrl-T = hex mode.".to_owned(),
+            );
+        } else if self.document.directory().is_some() {
+            self.status_message = StatusMessage::from(DIRECTORY_HELP.to_owned());
+        } else {
+            self.status_message = StatusMessage::from(self.describe_buffer());
+        }
+        Ok(())
+    }
+
+    // Closes the shown buffer, asking first if it has unsaved changes, and
+    // shows the next one.
+    fn close_buffer(&mut self) -> Result<(), std::io::Error> {
+        if self.document.is_dirty() {
+            let name = self.document.file_name.clone().unwrap_or_else(|| "[Untitled]".to_owned());
+            loop {
+                self.status_message = StatusMessage::from(format!(
+                    "{} has unsaved changes. (s)ave, (d)iscard or (c)ancel?",
+                    name
+                ));
+                self.refresh_screen()?;
+                match self.read_key()? {
+                    Key::Char('s') => {
+                        self.save();
+                        if self.document.is_dirty() {
+                            return Ok(());
+                        }
+                        break;
+                    },
+                    Key::Char('d') => break,
+                    Key::Char('c') | Key::Esc => {
+                        self.status_message = StatusMessage::from("Close aborted.".to_owned());
+                        return Ok(());
+                    },
+                    _ => (),
+                }
+            }
+        }
+        let _ = self.document.remove_swap();
+        // Windows showing the closed buffer show the next one instead.
+        let next = self.current.min(self.buffers.len().saturating_sub(1));
+        for window in &mut self.windows {
+            if window.buffer == self.current {
+                *window = Window {
+                    buffer: next,
+                    ..Window::default()
+                };
+            } else if window.buffer > self.current {
+                window.buffer = window.buffer.saturating_sub(1);
+            }
+        }
+        if self.buffers.is_empty() {
+            self.show_buffer(Buffer::new(Document::default()));
+            self.status_message = StatusMessage::from("Buffer closed.".to_owned());
+            return Ok(());
+        }
+        if self.current >= self.buffers.len() {
+            self.current = self.buffers.len().saturating_sub(1);
+        }
+        let buffer = self.buffers.remove(self.current);
+        self.show_buffer(buffer);
+        self.status_message = StatusMessage::from(format!("Buffer closed. {}", self.describe_buffer()));
+        Ok(())
+    }
+
+    fn list_buffers(&mut self) -> Result<(), std::io::Error> {
+        let describe = |document: &Document| {
+            format!(
+                "{}{}",
+                document.file_name.as_deref().unwrap_or("[Untitled]"),
+                if document.is_dirty() { " (modified)" } else { "" }
+            )
+        };
+        let mut items: Vec<String> = self.buffers.iter().map(|buffer| describe(&buffer.document)).collect();
+        items.insert(self.current, describe(&self.document));
+        let items = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| format!("{:>3} {}", index.saturating_add(1), item))
+            .collect();
+        if let Some(index) = self.pick("Buffers (Enter to show, ESC to cancel)", items, self.current)? {
+            self.switch_buffer(index);
+            self.status_message = StatusMessage::from(self.describe_buffer());
+        }
+        Ok(())
+    }
+
+    // Shows `items` in place of the text and lets the user pick one with the
+    // arrow keys and Enter. Returns `None` if the user cancels.
+    fn pick(&mut self, message: &str, items: Vec<String>, selected: usize) -> Result<Option<usize>, std::io::Error> {
+        let height = self.terminal.size().height as usize;
+        let mut picker = Picker::new(items);
+        picker.select(selected, height);
+        self.picker = Some(picker);
+        let result = loop {
+            self.status_message = StatusMessage::from(message.to_owned());
+            self.refresh_screen()?;
+            let key = self.read_key()?;
+            let Some(picker) = &mut self.picker else {
+                break None;
+            };
+            match key {
+                Key::Char('\n') if !picker.items.is_empty() => break picker.selected,
+                Key::Esc => break None,
+                key => {
+                    picker.navigate(key, height);
+                },
+            }
+        };
+        self.picker = None;
+        self.frame.invalidate();
+        self.status_message = StatusMessage::from(String::new());
+        Ok(result)
+    }
+
+    // Lets the user pick a file below the working directory by typing parts of
+    // its path, and opens it.
+    fn find_file(&mut self) -> Result<(), std::io::Error> {
+        let files = self.files.take().unwrap_or_else(|| {
+            finder::walk(Path::new(".")).into_iter().map(finder::Candidate::from).collect()
+        });
+        if files.is_empty() {
+            self.status_message = StatusMessage::from("No files found.".to_owned());
+            return Ok(());
+        }
+        self.show_matches(&files, "");
+        let query = self.prompt(
+            |editor| {
+                let matches = editor.picker.as_ref().map_or(0, |picker| picker.items.len());
+                format!("Find file ({} of {}, ESC to cancel): ", matches, files.len())
+            },
+            |editor, key, query| {
+                let height = editor.terminal.size().height as usize;
+                let navigated = editor
+                    .picker
+                    .as_mut()
+                    .is_some_and(|picker| picker.navigate(key, height));
+                if navigated {
+                    editor.preview_selected();
+                } else if editor.picker.as_ref().is_none_or(|picker| picker.query != *query) {
+                    editor.show_matches(&files, query);
+                }
+            },
+        );
+        let picker = self.picker.take();
+        self.files = Some(files);
+        self.frame.invalidate();
+        if query?.is_none() {
+            return Ok(());
+        }
+        let selected = picker.and_then(|picker| picker.selected.and_then(|index| picker.items.get(index).cloned()));
+        match selected {
+            Some(path) => self.open(&path),
+            None => {
+                self.status_message = StatusMessage::from("No matching files.".to_owned());
+                Ok(())
+            },
+        }
+    }
+
+    // Lists the files matching `query`, best first, and previews the best.
+    fn show_matches(&mut self, files: &[finder::Candidate], query: &str) {
+        let items = finder::rank(query, files)
+            .into_iter()
+            .take(MAX_MATCHES)
+            .filter_map(|index| files.get(index).map(|file| file.path.clone()))
+            .collect();
+        let mut picker = Picker::new(items);
+        picker.query = query.to_owned();
+        picker.select(0, self.terminal.size().height as usize);
+        self.picker = Some(picker);
+        self.preview_selected();
+    }
+
+    fn preview_selected(&mut self) {
+        let lines = self.terminal.size().height as usize;
+        if let Some(picker) = &mut self.picker {
+            let selected = picker.selected.and_then(|index| picker.items.get(index));
+            picker.preview = Some(selected.map_or_else(Vec::new, |path| finder::preview(path, lines)));
+        }
+    }
+
+    fn toggle_hex(&mut self) {
+        self.selection_anchor = None;
+        if self.document.hex().is_some() {
+            self.document.leave_hex();
+            self.cursor_position = self.text_position.clone();
+            self.move_cursor(Key::Null);
+            self.status_message = StatusMessage::from("Text mode.".to_owned());
+        } else if let Err(error) = self.document.enter_hex() {
+            self.status_message = StatusMessage::from(format!("Cannot show as hex: {}", error));
+        } else {
+            self.text_position = self.cursor_position.clone();
+            self.cursor_position = Position::default();
+            self.status_message = StatusMessage::from(
+                "Hex mode. Type hex digits to overwrite, Ctrl-F = find bytes, Ctrl-T = back to text".to_owned(),
+            );
+        }
+    }
+
+    // Handles a key in hex mode, where the cursor's `x` is the nibble within
+    // the line and `y` the line.
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    fn process_hex_keypress(&mut self, key: Key) -> Result<(), std::io::Error> {
+        let Some(len) = self.document.hex().map(HexBytes::len) else {
+            return Ok(());
+        };
+        let line = BYTES_PER_LINE * 2;
+        let page = line * self.text_height();
+        let last = (len * 2).saturating_sub(1);
+        let Position { x, y } = self.cursor_position;
+        let nibble = y * line + x;
+        let target = match key {
+            Key::Left => nibble.saturating_sub(1),
+            Key::Right => nibble + 1,
+            Key::Up => nibble.checked_sub(line).unwrap_or(nibble),
+            Key::Down if nibble + line <= last => nibble + line,
+            Key::PageUp => nibble.saturating_sub(page),
+            Key::PageDown => nibble + page,
+            Key::Home => nibble - x,
+            Key::End => nibble - x + line - 1,
+            Key::Char(c) if c.is_ascii_hexdigit() => {
+                let value = c.to_digit(16).and_then(|digit| u8::try_from(digit).ok()).unwrap_or(0);
+                if let Some(hex) = self.document.hex_mut() {
+                    hex.set_nibble(nibble / 2, nibble.is_multiple_of(2), value);
+                }
+                nibble + 1
+            },
+            Key::Ctrl('z') | Key::Ctrl('y') => {
+                let hex = self.document.hex_mut();
+                let offset = if key == Key::Ctrl('z') {
+                    hex.and_then(HexBytes::undo)
+                } else {
+                    hex.and_then(HexBytes::redo)
+                };
+                if let Some(offset) = offset {
+                    offset * 2
+                } else {
+                    self.status_message = StatusMessage::from("Nothing to undo or redo.".to_owned());
+                    nibble
+                }
+            },
+            Key::Ctrl('f') => self.search_bytes(nibble / 2)?.map_or(nibble, |offset| offset * 2),
+            _ => nibble,
+        };
+        let target = target.min(last);
+        self.cursor_position = Position {
+            x: target % line,
+            y: target / line,
+        };
+        Ok(())
+    }
+
+    // Prompts for bytes in hex and returns the offset of the next occurrence
+    // after `from`, wrapping around at the end.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn search_bytes(&mut self, from: usize) -> Result<Option<usize>, std::io::Error> {
+        let Some(query) = self
+            .prompt(|_| "Search bytes (hex, ESC to cancel): ".to_owned(), |_, _, _| {})?
+            .filter(|query| !query.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+        let Some(needle) = hex::parse(&query) else {
+            self.status_message = StatusMessage::from(format!("Invalid hex: {}", query));
+            return Ok(None);
+        };
+        let Some(bytes) = self.document.hex() else {
+            return Ok(None);
+        };
+        let found = bytes.find(&needle, from + 1).or_else(|| bytes.find(&needle, 0));
+        if found.is_none() {
+            self.status_message = StatusMessage::from(format!("Not found: {}", query));
+        } else if found.is_some_and(|found| found <= from) {
+            self.status_message = StatusMessage::from("Search wrapped.".to_owned());
+        }
+        Ok(found)
+    }
+
+    // Handles a key while the document lists a directory, with one entry per
+    // row.
+    fn process_directory_keypress(&mut self, key: Key) -> Result<(), std::io::Error> {
+        match key {
+            Key::Up | Key::Down | Key::PageUp | Key::PageDown => self.move_cursor(key),
+            Key::Home => self.cursor_position.y = 0,
+            Key::End => self.cursor_position.y = self.document.len().saturating_sub(1),
+            Key::Char('\n') => self.open_entry()?,
+            Key::Char('-') | Key::Backspace => {
+                let parent = self
+                    .document
+                    .directory()
+                    .and_then(|listing| listing.path().parent())
+                    .map(Path::to_path_buf);
+                if let Some(parent) = parent {
+                    self.browse(&parent)?;
+                }
+            },
+            Key::Char('c') => self.create_entry()?,
+            Key::Char('r') => self.rename_entry()?,
+            Key::Char('d') | Key::Delete => self.delete_entry()?,
+            _ => (),
+        }
+        self.cursor_position.x = 0;
+        Ok(())
+    }
+
+    // The path and name of the entry under the cursor, leaving out `..`.
+    fn selected_entry(&self) -> Option<(PathBuf, String)> {
+        let listing = self.document.directory()?;
+        let entry = listing.entry(self.cursor_position.y)?;
+        (entry.name != "..").then(|| (listing.path().join(&entry.name), entry.name.clone()))
+    }
+
+    fn open_entry(&mut self) -> Result<(), std::io::Error> {
+        let Some(listing) = self.document.directory() else {
+            return Ok(());
+        };
+        let Some(entry) = listing.entry(self.cursor_position.y) else {
+            return Ok(());
+        };
+        let path = listing.path().join(&entry.name);
+        if entry.is_dir {
+            // Go through the parent rather than `..` to keep the path short.
+            let path = if entry.name == ".." {
+                listing.path().parent().map_or(path, Path::to_path_buf)
+            } else {
+                path
+            };
+            self.browse(&path)
+        } else {
+            self.open(&directory::display(&path))
+        }
+    }
+
+    // Shows the listing of `path` in place of the current one, with the cursor
+    // on the directory just left when going up.
+    fn browse(&mut self, path: &Path) -> Result<(), std::io::Error> {
+        let left = self.document.directory().map(|listing| listing.path().to_path_buf());
+        match Document::open_directory(path) {
+            Ok(document) => {
+                self.document = document;
+                let y = left
+                    .as_deref()
+                    .filter(|left| left.parent() == Some(path))
+                    .and_then(Path::file_name)
+                    .and_then(|name| self.document.directory()?.position(&name.to_string_lossy()))
+                    .unwrap_or(0);
+                self.cursor_position = Position { x: 0, y };
+                self.offset = Position::default();
+                self.status_message = StatusMessage::from(DIRECTORY_HELP.to_owned());
+            },
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("Could not list {}: {}", path.display(), error));
+            },
+        }
+        Ok(())
+    }
+
+    // Lists the directory again after a change, with the cursor on `name`.
+    fn reload_directory(&mut self, name: Option<&str>) {
+        self.files = None;
+        let Some(path) = self.document.directory().map(|listing| listing.path().to_path_buf()) else {
+            return;
+        };
+        let y = self.cursor_position.y;
+        match Document::open_directory(&path) {
+            Ok(document) => self.document = document,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("Could not list {}: {}", path.display(), error));
+                return;
+            },
+        }
+        let y = name
+            .and_then(|name| self.document.directory()?.position(name))
+            .unwrap_or(y)
+            .min(self.document.len().saturating_sub(1));
+        self.cursor_position = Position { x: 0, y };
+    }
+
+    // Asks a yes-or-no question in the message bar.
+    fn confirm(&mut self, question: &str) -> Result<bool, std::io::Error> {
+        self.status_message = StatusMessage::from(format!("{} (y/n)", question));
+        self.refresh_screen()?;
+        let confirmed = matches!(self.read_key()?, Key::Char('y' | 'Y'));
+        self.status_message = StatusMessage::from(String::new());
+        Ok(confirmed)
+    }
+
+    fn create_entry(&mut self) -> Result<(), std::io::Error> {
+        let Some(directory) = self.document.directory().map(|listing| listing.path().to_path_buf()) else {
+            return Ok(());
+        };
+        let Some(name) = self
+            .prompt(|_| "Create (end with / for a directory): ".to_owned(), |_, _, _| {})?
+            .filter(|name| !name.is_empty())
+        else {
+            self.status_message = StatusMessage::from("Create aborted.".to_owned());
+            return Ok(());
+        };
+        let (name, is_dir) = name.strip_suffix('/').map_or((name.as_str(), false), |name| (name, true));
+        if !directory::is_entry_name(name) {
+            self.status_message = StatusMessage::from(format!("Invalid name: {}", name));
+            return Ok(());
+        }
+        let path = directory.join(name);
+        let kind = if is_dir { "directory" } else { "file" };
+        if !self.confirm(&format!("Create {} {}?", kind, name))? {
+            self.status_message = StatusMessage::from("Create aborted.".to_owned());
+            return Ok(());
+        }
+        let result = if is_dir {
+            fs::create_dir(&path)
+        } else {
+            OpenOptions::new().write(true).create_new(true).open(&path).map(|_| ())
+        };
+        self.status_message = match result {
+            Ok(()) => StatusMessage::from(format!("Created {}.", name)),
+            Err(error) => StatusMessage::from(format!("Could not create {}: {}", name, error)),
+        };
+        self.reload_directory(Some(name));
+        Ok(())
+    }
+
+    fn rename_entry(&mut self) -> Result<(), std::io::Error> {
+        let Some((path, name)) = self.selected_entry() else {
+            return Ok(());
+        };
+        let Some(new_name) = self
+            .prompt(|_| format!("Rename {} to: ", name), |_, _, _| {})?
+            .filter(|new_name| !new_name.is_empty() && *new_name != name)
+        else {
+            self.status_message = StatusMessage::from("Rename aborted.".to_owned());
+            return Ok(());
+        };
+        if !directory::is_entry_name(&new_name) {
+            self.status_message = StatusMessage::from(format!("Invalid name: {}", new_name));
+            return Ok(());
+        }
+        let new_path = path.with_file_name(&new_name);
+        if new_path.symlink_metadata().is_ok() {
+            self.status_message = StatusMessage::from(format!("{} already exists.", new_name));
+            return Ok(());
+        }
+        if !self.confirm(&format!("Rename {} to {}?", name, new_name))? {
+            self.status_message = StatusMessage::from("Rename aborted.".to_owned());
+            return Ok(());
+        }
+        self.status_message = match fs::rename(&path, &new_path) {
+            Ok(()) => StatusMessage::from(format!("Renamed {} to {}.", name, new_name)),
+            Err(error) => StatusMessage::from(format!("Could not rename {}: {}", name, error)),
+        };
+        self.reload_directory(Some(&new_name));
+        Ok(())
+    }
+
+    fn delete_entry(&mut self) -> Result<(), std::io::Error> {
+        let Some((path, name)) = self.selected_entry() else {
+            return Ok(());
+        };
+        // Directories are only removed when empty.
+        let is_dir = path.symlink_metadata().is_ok_and(|metadata| metadata.is_dir());
+        let kind = if is_dir { "directory" } else { "file" };
+        if !self.confirm(&format!("Delete {} {}?", kind, name))? {
+            self.status_message = StatusMessage::from("Delete aborted.".to_owned());
+            return Ok(());
+        }
+        let result = if is_dir {
+            fs::remove_dir(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        self.status_message = match result {
+            Ok(()) => StatusMessage::from(format!("Deleted {}.", name)),
+            Err(error) => StatusMessage::from(format!("Could not delete {}: {}", name, error)),
+        };
+        self.reload_directory(None);
+        Ok(())
+    }
+
+    fn select_encoding(&mut self) -> Result<(), std::io::Error> {
+        self.status_message = StatusMessage::from(format!(
+            "Save as {}? (u)tf-8, utf-16 (l)e/(b)e, (w)indows-1252 or lat(i)n-1 (ESC to cancel)",
+            self.document.encoding()
+        ));
+        self.refresh_screen()?;
+        let encoding = match self.read_key()? {
+            Key::Char('u') => Encoding::Utf8,
+            Key::Char('l') => Encoding::Utf16Le,
+            Key::Char('b') => Encoding::Utf16Be,
+            Key::Char('w') => Encoding::Windows1252,
+            Key::Char('i') => Encoding::Latin1,
+            _ => {
+                self.status_message = StatusMessage::from("Encoding unchanged.".to_owned());
+                return Ok(());
+            },
+        };
+        self.document.set_encoding(encoding);
+        self.status_message = StatusMessage::from(format!("The file will be saved as {}.", encoding.name()));
+        Ok(())
+    }
+
+    fn convert_line_endings(&mut self) -> Result<(), std::io::Error> {
+        self.status_message = StatusMessage::from(format!(
+            "Line endings are {}. Convert to (l)f or (c)rlf? (ESC to cancel)",
+            self.document.line_endings()
+        ));
+        self.refresh_screen()?;
+        let ending = match self.read_key()? {
+            Key::Char('l') => LineEnding::Lf,
+            Key::Char('c') => LineEnding::Crlf,
+            _ => {
+                self.status_message = StatusMessage::from("Conversion aborted.".to_owned());
+                return Ok(());
+            },
+        };
+        self.document.set_line_ending(ending);
+        self.status_message = StatusMessage::from(format!("Line endings converted to {}.", ending.name()));
+        Ok(())
+    }
+
+    // Reads the next key, redrawing the screen whenever the terminal is resized
+    // in the meantime.
+    fn read_key(&mut self) -> Result<Key, std::io::Error> {
+        loop {
+            match self.terminal.read_event()? {
+                Event::Key(key) => return Ok(key),
+                Event::Resize => {
+                    self.scroll();
+                    self.terminal.clear_screen();
+                    self.frame.invalidate();
+                    self.refresh_screen()?;
+                },
+                Event::Idle => self.update_swap()?,
+            }
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
     fn process_keypress(&mut self) -> Result<(), std::io::Error> {
-        let pressed_key = Terminal::read_key()?;
+        let pressed_key = self.read_key()?;
+        let before = (self.current, self.document.len());
         match pressed_key {
+            key if self.document.hex().is_some()
+                && !matches!(key, Key::Ctrl('q' | 's' | 't' | 'o' | 'p' | 'b' | 'k' | 'w') | Key::Alt('n' | 'p')) =>
+            {
+                self.process_hex_keypress(key)?;
+            },
+            key if self.document.directory().is_some()
+                && !matches!(key, Key::Ctrl('q' | 'f' | 'n' | 'o' | 'p' | 'b' | 'k' | 'w') | Key::Alt('n' | 'p')) =>
+            {
+                self.process_directory_keypress(key)?;
+            },
             Key::Ctrl('q') => {
-                if self.quit_confirm > 0 && self.document.is_dirty() {
+                let dirty = self.dirty_buffers();
+                if self.quit_confirm > 0 && dirty > 0 {
+                    let what = match dirty {
+                        1 if self.document.is_dirty() => "File has".to_owned(),
+                        1 => "Another buffer has".to_owned(),
+                        _ => format!("{} buffers have", dirty),
+                    };
                     self.status_message = StatusMessage::from(format!(
-                        "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
-                        self.quit_confirm
+                        "WARNING! {} unsaved changes. Press Ctrl-Q {} more times to quit.",
+                        what, self.quit_confirm
                     ));
                     self.quit_confirm -= 1;
                     return Ok(());
                 }
+                self.should_quit = true;
             },
             Key::Ctrl('s') => self.save(),
             Key::Ctrl('f') => self.search(),
+            Key::Ctrl('r') => self.replace()?,
+            Key::Ctrl('l') => self.convert_line_endings()?,
+            Key::Ctrl('e') => self.select_encoding()?,
+            Key::Ctrl('t') => self.toggle_hex(),
+            Key::Ctrl('o') => self.open_prompt()?,
+            Key::Ctrl('p') => self.find_file()?,
+            Key::Ctrl('b') => self.list_buffers()?,
+            Key::Ctrl('k') => self.close_buffer()?,
+            Key::Ctrl('w') => self.window_command()?,
+            Key::Alt('n') => self.cycle_buffer(true),
+            Key::Alt('p') => self.cycle_buffer(false),
+            Key::Ctrl('n') => self.cycle_line_numbers(),
+            Key::Null => self.toggle_selection(),
+            Key::Esc => self.selection_anchor = None,
+            Key::Ctrl('c') => self.copy_selection(),
+            Key::Ctrl('x') => self.cut_selection(),
+            Key::Ctrl('v') => self.paste(),
+            Key::Char('\t') if self.config.expand_tabs => {
+                self.delete_selection();
+                let tab_width = self.config.tab_width.max(1);
+                let spaces = tab_width.saturating_sub(self.cursor_column() % tab_width);
+                self.cursor_position = self.document.insert_str(&self.cursor_position, &" ".repeat(spaces));
+            },
             Key::Char(c) => {
+                self.delete_selection();
                 self.document.insert(&self.cursor_position, c);
                 self.move_cursor(Key::Right);
             },
+            Key::Delete | Key::Backspace if self.selection().is_some() => {
+                self.delete_selection();
+            },
             Key::Delete => self.document.delete(&self.cursor_position),
             Key::Backspace => {
-                if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
-                    self.move_cursor(Key::Left);
-                    self.document.delete(&self.cursor_position);
+                if let Some(position) = self.document.backspace(&self.cursor_position) {
+                    self.cursor_position = position;
+                }
+            },
+            Key::Ctrl('z') => {
+                self.selection_anchor = None;
+                if let Some(position) = self.document.undo() {
+                    self.cursor_position = position;
+                } else {
+                    self.status_message = StatusMessage::from("Nothing to undo.".to_owned());
+                }
+            },
+            Key::Ctrl('y') => {
+                self.selection_anchor = None;
+                if let Some(position) = self.document.redo() {
+                    self.cursor_position = position;
+                } else {
+                    self.status_message = StatusMessage::from("Nothing to redo.".to_owned());
                 }
             },
             Key::Up
@@ -187,6 +1609,9 @@ impl Editor {
             _ => (),
         }
 
+        self.follow_edits(before);
+        // Input may never pause while someone types steadily.
+        self.update_swap()?;
         self.scroll();
         if self.quit_confirm < QUIT_CONFIRM {
             self.quit_confirm = QUIT_CONFIRM;
@@ -195,11 +1620,180 @@ impl Editor {
         Ok(())
     }
 
-    fn scroll(&mut self) {
+    // Keeps the other windows on the current buffer showing the same text
+    // after an edit: their positions past the first edited row move with the
+    // rows added or removed, and all of them stay within the document.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn follow_edits(&mut self, (buffer, len): (usize, usize)) {
+        let Some(first) = self.document.take_edited() else {
+            return;
+        };
+        // Hex edits overwrite bytes in place.
+        if buffer != self.current || self.document.hex().is_some() {
+            return;
+        }
+        let new_len = self.document.len();
+        let shift = |y: usize| {
+            if y <= first {
+                y
+            } else if new_len >= len {
+                y + (new_len - len)
+            } else {
+                y.saturating_sub(len - new_len).max(first)
+            }
+        };
+        for (index, window) in self.windows.iter_mut().enumerate() {
+            if index == self.window || window.buffer != self.current {
+                continue;
+            }
+            let y = shift(window.cursor_position.y).min(new_len);
+            let x = window.cursor_position.x.min(self.document.row(y).map_or(0, Row::len));
+            window.cursor_position = Position { x, y };
+            window.offset.y = shift(window.offset.y).min(y);
+        }
+    }
+
+    fn dirty_buffers(&self) -> usize {
+        let others = self.buffers.iter().filter(|buffer| buffer.document.is_dirty()).count();
+        others.saturating_add(usize::from(self.document.is_dirty()))
+    }
+
+    fn cycle_line_numbers(&mut self) {
+        self.line_numbers = self.line_numbers.next();
+        let mode = match self.line_numbers {
+            LineNumbers::Off => "off",
+            LineNumbers::Absolute => "absolute",
+            LineNumbers::Relative => "relative",
+        };
+        self.status_message = StatusMessage::from(format!("Line numbers: {}.", mode));
+    }
+
+    // Columns taken by the line number gutter: the digits of the last line
+    // number plus a separating space.
+    fn gutter_width(&self, document: &Document) -> usize {
+        if self.line_numbers == LineNumbers::Off {
+            return 0;
+        }
+        let digits = document.len().max(1).to_string().len();
+        digits.saturating_add(1)
+    }
+
+    // Columns left for the text of the current window once the gutter is drawn.
+    fn text_width(&self) -> usize {
+        self.area().width.saturating_sub(self.gutter_width(&self.document))
+    }
+
+    // Lines of text the current window shows above its status bar.
+    fn text_height(&self) -> usize {
+        self.area().height.saturating_sub(1)
+    }
+
+    fn toggle_selection(&mut self) {
+        if self.selection_anchor.take().is_some() {
+            self.status_message = StatusMessage::from("Selection cleared.".to_owned());
+        } else {
+            self.selection_anchor = Some(self.cursor_position.clone());
+            self.status_message = StatusMessage::from(
+                "Selecting. Ctrl-X/Ctrl-C = cut/copy, Ctrl-Space/ESC = cancel".to_owned(),
+            );
+        }
+    }
+
+    // Returns the selected range in document order, if anything is selected.
+    fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = self.selection_anchor.clone()?;
+        let cursor = self.cursor_position.clone();
+        let (start, mut end) = if (anchor.y, anchor.x) <= (cursor.y, cursor.x) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        // The line after the last row selects up to the end of the document.
+        if end.y >= self.document.len() {
+            end.y = self.document.len().saturating_sub(1);
+            end.x = self.document.row(end.y).map_or(0, Row::len);
+        }
+        ((start.y, start.x) < (end.y, end.x)).then_some((start, end))
+    }
+
+    fn copy_selection(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            self.clipboard = self.document.text(&start, &end);
+            self.selection_anchor = None;
+            self.status_message = StatusMessage::from(format!("Copied selection{}.", self.export_clipboard()));
+        }
+    }
+
+    fn cut_selection(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            self.clipboard = self.document.delete_range(&start, &end);
+            self.cursor_position = start;
+            self.selection_anchor = None;
+            self.status_message = StatusMessage::from(format!("Cut selection{}.", self.export_clipboard()));
+        }
+    }
+
+    // Sends the clipboard to the system clipboard through the terminal, falling
+    // back to the configured command, and describes the outcome.
+    fn export_clipboard(&self) -> String {
+        if self.terminal.copy_to_clipboard(&self.clipboard, self.config.osc52_limit) {
+            return String::new();
+        }
+        match &self.config.clipboard_command {
+            Some(command) => match clipboard::pipe_to_command(command, &self.clipboard) {
+                Ok(()) => String::new(),
+                Err(error) => format!(" (clipboard command failed: {})", error),
+            },
+            None => " (too large for the system clipboard)".to_owned(),
+        }
+    }
+
+    fn paste(&mut self) {
+        if self.clipboard.is_empty() {
+            self.status_message = StatusMessage::from("Clipboard is empty.".to_owned());
+            return;
+        }
+        self.delete_selection();
+        self.cursor_position = self.document.insert_str(&self.cursor_position, &self.clipboard);
+    }
+
+    fn delete_selection(&mut self) {
+        let selection = self.selection();
+        self.selection_anchor = None;
+        if let Some((start, end)) = selection {
+            self.document.delete_range(&start, &end);
+            self.cursor_position = start;
+        }
+    }
+
+    // The render column of the cursor, which differs from its grapheme index
+    // once tabs expand.
+    fn cursor_column(&self) -> usize {
         let Position { x, y } = self.cursor_position;
-        let width = self.terminal.size().width as usize;
-        let height = self.terminal.size().height as usize;
-        let mut offset = &mut self.offset;
+        self.document
+            .row(y)
+            .map_or(0, |row| row.column(x, self.config.tab_width))
+    }
+
+    fn scroll(&mut self) {
+        let y = self.cursor_position.y;
+        // The last column of the grapheme under the cursor, so a wide one is
+        // scrolled fully into view. Hex lines never scroll sideways.
+        let (x, x_end) = if self.document.hex().is_some() {
+            (0, 0)
+        } else {
+            let x = self.cursor_column();
+            let x_end = self
+                .document
+                .row(y)
+                .map_or(x, |row| row.column(self.cursor_position.x.saturating_add(1), self.config.tab_width))
+                .saturating_sub(1)
+                .max(x);
+            (x, x_end)
+        };
+        let width = self.text_width();
+        let height = self.text_height();
+        let offset = &mut self.offset;
 
         if y < offset.y {
             offset.y = y;
@@ -209,13 +1803,13 @@ impl Editor {
 
         if x < offset.x {
             offset.x = x;
-        } else if x >= offset.x.saturating_add(width) {
-            offset.x = x.saturating_sub(width).saturating_add(1);
+        } else if x_end >= offset.x.saturating_add(width) {
+            offset.x = x_end.saturating_sub(width).saturating_add(1).min(x);
         }
     }
 
     fn move_cursor(&mut self, key: Key) {
-        let terminal_height = self.terminal.size().height as usize;
+        let terminal_height = self.text_height();
         let Position { mut x, mut y } = self.cursor_position;
 
         // Prevent the cursor from moving off screen
@@ -229,6 +1823,7 @@ impl Editor {
 
         match key {
             Key::Up => y = y.saturating_sub(1),
+            #[allow(clippy::collapsible_match)]
             Key::Down => {
                 if y < height {
                     y = y.saturating_add(1);
@@ -273,6 +1868,14 @@ impl Editor {
             _ => (),
         }
 
+        // Moving between lines keeps the cursor in the same render column.
+        if y != self.cursor_position.y && matches!(key, Key::Up | Key::Down | Key::PageUp | Key::PageDown) {
+            let column = self.cursor_column();
+            if let Some(row) = self.document.row(y) {
+                x = row.index_at_column(column, self.config.tab_width);
+            }
+        }
+
         // Reset `width` in case text was entered
         width = if let Some(row) = self.document.row(y) {
             row.len()
@@ -288,123 +1891,229 @@ impl Editor {
         self.cursor_position = Position { x, y }
     }
 
-    fn draw_welcome_message(&self) {
+    fn draw_welcome_message(&self, width: usize) -> String {
         let mut welcome_message = format!("Hecto editor -- version {}", VERSION);
-        let width = self.terminal.size().width as usize;
         let len = welcome_message.len();
-        #[allow(clippy::integer_arithmetic, clippy::integer_division)]
+        #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
         let padding = width.saturating_sub(len) / 2;
         let spaces = " ".repeat(padding.saturating_sub(1));
         welcome_message = format!("~{}{}", spaces, welcome_message);
         welcome_message.truncate(width);
-        println!("{}\r", welcome_message);
+        welcome_message
     }
 
-    fn draw_row(&self, row: &Row) {
-        let width = self.terminal.size().width as usize;
-        let start = self.offset.x;
-        let end = self.offset.x.saturating_add(width);
-        let row = row.render(start, end);
+    fn draw_row(&self, view: &View, row: &Row, y: usize) -> String {
+        let width = view.width.saturating_sub(self.gutter_width(view.document));
+        let start = view.offset.x;
+        let end = view.offset.x.saturating_add(width);
+        let tab_width = self.config.tab_width;
+        let overlays = if view.active {
+            self.overlays(row, y, row.index_at_column(end, tab_width))
+        } else {
+            Vec::new()
+        };
+        let text = row.render(start, end, tab_width, &overlays);
+        format!("{}{}", self.draw_gutter(view, y), text)
+    }
 
-        println!("{}\r", row);
+    fn draw_gutter(&self, view: &View, y: usize) -> String {
+        let cursor = view.cursor_position.y;
+        let number = match self.line_numbers {
+            LineNumbers::Off => return String::new(),
+            LineNumbers::Relative if y != cursor => y.abs_diff(cursor),
+            LineNumbers::Absolute | LineNumbers::Relative => y.saturating_add(1),
+        };
+        let digits = self.gutter_width(view.document).saturating_sub(1);
+        if y == cursor {
+            format!("{:>digits$} ", number, digits = digits)
+        } else {
+            format!(
+                "{}{:>digits$}{} ",
+                color::Fg(color::LightBlack),
+                number,
+                color::Fg(color::Reset),
+                digits = digits
+            )
+        }
     }
 
-    #[allow(clippy::integer_arithmetic, clippy::integer_division)]
-    fn draw_rows(&self) {
-        let height = self.terminal.size().height;
+    // Spans on row `y` drawn over the syntax highlighting: search matches that
+    // start before `end`, the current match and the selection.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn overlays(&self, row: &Row, y: usize, end: usize) -> Vec<(Range<usize>, highlighting::Type)> {
+        let mut overlays = Vec::new();
+        if let Some(query) = &self.search_state.query {
+            for (start, len) in row.matches(query).take_while(|&(start, _)| start < end) {
+                if len > 0 {
+                    overlays.push((start..start + len, highlighting::Type::Match));
+                }
+            }
+        }
+        if let Some((position, len)) = &self.search_state.current {
+            if position.y == y {
+                overlays.push((position.x..position.x + len, highlighting::Type::CurrentMatch));
+            }
+        }
+        if let Some((start, end)) = self.selection() {
+            if (start.y..=end.y).contains(&y) {
+                let from = if y == start.y { start.x } else { 0 };
+                let to = if y == end.y { end.x } else { row.len() };
+                overlays.push((from..to, highlighting::Type::Selection));
+            }
+        }
+        overlays
+    }
+
+    fn draw_picker(&self, picker: &Picker, area: &Rect) -> Vec<String> {
+        (0..area.height)
+            .map(|row| {
+                let index = picker.offset.saturating_add(row);
+                match picker.items.get(index) {
+                    Some(item) if picker.selected == Some(index) => {
+                        format!("{}{}{}", style::Invert, item, style::Reset)
+                    },
+                    Some(item) => item.clone(),
+                    None => "~".to_owned(),
+                }
+            })
+            .collect()
+    }
+
+    fn draw_preview(&self, rows: &[Row], area: &Rect) -> Vec<String> {
+        rows.iter()
+            .take(area.height)
+            .map(|row| row.render(0, area.width, self.config.tab_width, &[]))
+            .collect()
+    }
+
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    fn draw_rows(&self, view: &View) -> Vec<String> {
+        let height = view.height;
+        let mut lines = Vec::with_capacity(height.saturating_add(1));
+        let document = view.document;
+        let cursor_byte = (view.cursor_position.y * BYTES_PER_LINE * 2 + view.cursor_position.x) / 2;
         for terminal_row in 0..height {
-            Terminal::clear_current_line();
-            if let Some(row) = self.document.row(self.offset.y.saturating_add(terminal_row as usize)) {
-                self.draw_row(row);
-            } else if self.document.is_empty() && terminal_row == height / 3 {
-                self.draw_welcome_message();
+            let y = view.offset.y.saturating_add(terminal_row);
+            let line = if let Some(hex) = document.hex() {
+                if y < hex.lines() {
+                    hex.render_line(y, cursor_byte)
+                } else {
+                    "~".to_owned()
+                }
+            } else if let Some(row) = document.row(y) {
+                self.draw_row(view, row, y)
+            } else if document.is_empty() && terminal_row == height / 3 {
+                self.draw_welcome_message(view.width)
             } else {
-                println!("~\r");
-            }
+                "~".to_owned()
+            };
+            lines.push(line);
         }
+        lines
     }
 
-    fn draw_status_bar(&self) {
+    fn draw_status_bar(&self, view: &View) -> String {
         let mut status;
-        let width = self.terminal.size().width as usize;
-        let modified_indicator = if self.document.is_dirty() {
+        let width = view.width;
+        let document = view.document;
+        let modified_indicator = if document.is_read_only() {
+            " (read-only)"
+        } else if document.is_dirty() {
             " (modified)"
         } else {
             ""
         };
         let mut file_name = "[Untitled]".to_owned();
-        if let Some(name) = &self.document.file_name {
+        if let Some(name) = &document.file_name {
             file_name = name.clone();
             file_name.truncate(20);
         }
 
-        status = format!("{} | {} lines{}", file_name, self.document.len(), modified_indicator);
-        let line_indicator = format!(
-            "{}:{}",
-            self.cursor_position.y.saturating_add(1),
-            self.document.len()
-        );
-        #[allow(clippy::integer_arithmetic)]
+        status = format!("{} | {} lines{}", file_name, document.len(), modified_indicator);
+        if !self.buffers.is_empty() {
+            status = format!("[{}/{}] {}", view.buffer.saturating_add(1), self.buffer_count(), status);
+        }
+        let line_indicator = if let Some(hex) = document.hex() {
+            #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+            let offset = (view.cursor_position.y * BYTES_PER_LINE * 2 + view.cursor_position.x) / 2;
+            format!("HEX | {:#010x} | {} bytes", offset, hex.len())
+        } else if document.directory().is_some() {
+            format!("DIR | {}/{} entries", view.cursor_position.y.saturating_add(1), document.len())
+        } else {
+            format!(
+                "{} | {} | {} | {}:{}",
+                document.file_type(),
+                document.encoding(),
+                document.line_endings(),
+                view.cursor_position.y.saturating_add(1),
+                document.len()
+            )
+        };
+        #[allow(clippy::arithmetic_side_effects)]
         let len = status.len() + line_indicator.len();
         status.push_str(&" ".repeat(width.saturating_sub(len)));
         status = format!("{}{}", status, line_indicator);
         status.truncate(width);
 
-        Terminal::invert_style();
-
-        println!("{}\r", status);
+        // With several windows, the current one has a bold status bar.
+        if view.active && self.windows.len() > 1 {
+            format!("{}{}{}{}", style::Invert, style::Bold, status, style::Reset)
+        } else {
+            format!("{}{}{}", style::Invert, status, style::Reset)
+        }
     }
 
-    fn draw_message_bar(&self) {
-        Terminal::clear_current_line();
+    fn draw_message_bar(&self) -> String {
         let message = &self.status_message;
-        if Instant::now() - message.time < Duration::new(5, 0) {
+        let width = self.terminal.size().width as usize;
+        let text = if Instant::now() - message.time < Duration::new(5, 0) {
             let mut text = message.text.clone();
-            text.truncate(self.terminal.size().width as usize);
-            text = format!("{}{}", text, " ".repeat(self.terminal.size().width as usize - text.len()));
-            print!("{}", text);
-            
+            text.truncate(width);
+            text
         } else {
-            print!("{}", " ".repeat(self.terminal.size().width as usize));
-        }
-        
-        Terminal::reset_style();
+            String::new()
+        };
+        format!("{}{:width$}{}", style::Invert, text, style::Reset, width = width)
     }
 
-    fn prompt<C>(&mut self, prompt: &str, callback: C) -> Result<Option<String>, std::io::Error>
-    where C: Fn(&mut Self, Key, &String), {
+    fn prompt<P, C>(&mut self, prompt: P, callback: C) -> Result<Option<String>, std::io::Error>
+    where P: Fn(&Self) -> String, C: Fn(&mut Self, Key, &mut String), {
         let mut result = String::new();
+        let mut cancelled = false;
 
         loop {
-            self.status_message = StatusMessage::from(format!("{}{}", prompt, result));
+            self.status_message = StatusMessage::from(format!("{}{}", prompt(self), result));
             self.refresh_screen()?;
 
-            let key = Terminal::read_key()?;
+            let key = self.read_key()?;
             match key {
-                Key::Backspace => result.truncate(result.len().saturating_sub(1)),
+                Key::Backspace => {
+                    result.pop();
+                },
                 Key::Char('\n') => break,
+                #[allow(clippy::collapsible_match)]
                 Key::Char(c) => {
                     if !c.is_control() {
                         result.push(c);
                     }
                 },
                 Key::Esc => {
-                    result.truncate(0);
+                    cancelled = true;
                     break;
                 },
                 _ => (),
             }
-            callback(self, key, &result);
+            callback(self, key, &mut result);
         }
         self.status_message = StatusMessage::from(String::new());
-        if result.is_empty() {
+        if cancelled {
             return Ok(None);
         }
         Ok(Some(result))
     }
-}
 
-fn die(e: std::io::Error) {
-    Terminal::clear_screen();
-    panic!("{}", e);
+    fn die(&self, e: &std::io::Error) {
+        self.terminal.clear_screen();
+        panic!("{}", e);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,242 @@
+use std::io::{Error, ErrorKind};
+
+// Characters for bytes 0x80..=0x9F in Windows-1252. The five bytes it leaves
+// undefined map to the C1 control with the same value, as in Latin-1, so any
+// byte sequence decodes and encodes back unchanged.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+    Latin1,
+}
+
+// The text of a file along with how it was stored. A leading byte order mark
+// is decoded into the text as U+FEFF.
+pub struct Decoded {
+    pub text: String,
+    pub encoding: Encoding,
+    // Whether the file looks like binary data rather than text.
+    pub binary: bool,
+}
+
+impl Encoding {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Windows1252 => "Windows-1252",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+
+    // Whether files in this encoding always start with a byte order mark.
+    #[must_use]
+    pub fn needs_bom(self) -> bool {
+        matches!(self, Self::Utf16Le | Self::Utf16Be)
+    }
+
+    // Picks the encoding of `bytes`: UTF-16 when it starts with a byte order
+    // mark, UTF-8 when valid, and Windows-1252 otherwise. Files containing NUL
+    // bytes are treated as binary and read as Latin-1, one character per byte.
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Decoded {
+        let utf16 = match bytes {
+            [0xff, 0xfe, ..] => Some(Self::Utf16Le),
+            [0xfe, 0xff, ..] => Some(Self::Utf16Be),
+            _ => None,
+        };
+        if let Some(text) = utf16.and_then(|encoding| encoding.decode(bytes)) {
+            return Decoded {
+                text,
+                encoding: utf16.unwrap_or_default(),
+                binary: false,
+            };
+        }
+        if bytes.contains(&0) {
+            return Decoded {
+                text: Self::Latin1.decode(bytes).unwrap_or_default(),
+                encoding: Self::Latin1,
+                binary: true,
+            };
+        }
+        match Self::Utf8.decode(bytes) {
+            Some(text) => Decoded {
+                text,
+                encoding: Self::Utf8,
+                binary: false,
+            },
+            None => Decoded {
+                text: Self::Windows1252.decode(bytes).unwrap_or_default(),
+                encoding: Self::Windows1252,
+                binary: false,
+            },
+        }
+    }
+
+    // Decodes `bytes`, or returns `None` if they are not valid in this encoding.
+    #[must_use]
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            Self::Utf16Le | Self::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return None;
+                }
+                let units = bytes.chunks_exact(2).map(|pair| {
+                    let pair = [pair.first().copied().unwrap_or(0), pair.get(1).copied().unwrap_or(0)];
+                    if self == Self::Utf16Le {
+                        u16::from_le_bytes(pair)
+                    } else {
+                        u16::from_be_bytes(pair)
+                    }
+                });
+                char::decode_utf16(units).collect::<Result<String, _>>().ok()
+            },
+            Self::Windows1252 => Some(bytes.iter().map(|&byte| windows_1252(byte)).collect()),
+            Self::Latin1 => Some(bytes.iter().map(|&byte| char::from(byte)).collect()),
+        }
+    }
+
+    // Appends `text` encoded in this encoding to `output`, failing on the first
+    // character the encoding cannot represent.
+    pub fn encode(self, text: &str, output: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            Self::Utf8 => output.extend_from_slice(text.as_bytes()),
+            Self::Utf16Le => text.encode_utf16().for_each(|unit| output.extend(unit.to_le_bytes())),
+            Self::Utf16Be => text.encode_utf16().for_each(|unit| output.extend(unit.to_be_bytes())),
+            Self::Windows1252 | Self::Latin1 => {
+                for c in text.chars() {
+                    let byte = if self == Self::Windows1252 {
+                        to_windows_1252(c)
+                    } else {
+                        u8::try_from(u32::from(c)).ok()
+                    };
+                    let Some(byte) = byte else {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("cannot encode {:?} in {}", c, self.name()),
+                        ));
+                    };
+                    output.push(byte);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+fn windows_1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9f => WINDOWS_1252
+            .get(usize::from(byte & 0x1f))
+            .copied()
+            .unwrap_or(char::REPLACEMENT_CHARACTER),
+        _ => char::from(byte),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::arithmetic_side_effects)]
+fn to_windows_1252(c: char) -> Option<u8> {
+    match u32::from(c) {
+        code @ (0..=0x7f | 0xa0..=0xff) => u8::try_from(code).ok(),
+        _ => WINDOWS_1252
+            .iter()
+            .position(|&mapped| mapped == c)
+            .map(|index| 0x80 + index as u8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Windows1252,
+        Encoding::Latin1,
+    ];
+
+    fn encode(encoding: Encoding, text: &str) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        encoding.encode(text, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn text_round_trips_through_every_encoding() {
+        for encoding in ALL {
+            for text in ["", "plain\r\nlines\n", "café ÿ\t"] {
+                let bytes = encode(encoding, text).unwrap();
+                assert_eq!(encoding.decode(&bytes).as_deref(), Some(text), "{}", encoding.name());
+            }
+        }
+        for encoding in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+            for text in ["日本語 \u{1f44d}", "\u{feff}bom"] {
+                assert_eq!(encoding.decode(&encode(encoding, text).unwrap()).as_deref(), Some(text));
+            }
+        }
+    }
+
+    #[test]
+    fn single_byte_encodings_keep_every_byte() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for encoding in [Encoding::Windows1252, Encoding::Latin1] {
+            let text = encoding.decode(&bytes).unwrap();
+            assert_eq!(text.chars().count(), 256);
+            assert_eq!(encode(encoding, &text).unwrap(), bytes);
+        }
+        assert_eq!(Encoding::Windows1252.decode(&[0x80, 0x9f, 0x81]).as_deref(), Some("€Ÿ\u{81}"));
+        assert_eq!(encode(Encoding::Windows1252, "€").unwrap(), [0x80]);
+    }
+
+    #[test]
+    fn encoding_fails_on_characters_it_cannot_hold() {
+        assert!(encode(Encoding::Latin1, "€").is_err());
+        assert!(encode(Encoding::Windows1252, "日").is_err());
+        assert_eq!(
+            encode(Encoding::Latin1, "a€").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn invalid_bytes_do_not_decode() {
+        assert_eq!(Encoding::Utf8.decode(&[0x61, 0xff]), None);
+        assert_eq!(Encoding::Utf16Le.decode(&[0x61, 0x00, 0x62]), None);
+        // An unpaired surrogate.
+        assert_eq!(Encoding::Utf16Le.decode(&[0x00, 0xd8]), None);
+        assert_eq!(Encoding::Utf16Be.decode(&[0x00, 0x61]).as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn detect_picks_the_encoding() {
+        let detected = |bytes: &[u8]| {
+            let decoded = Encoding::detect(bytes);
+            (decoded.encoding, decoded.text, decoded.binary)
+        };
+        assert!(detected(b"caf\xc3\xa9") == (Encoding::Utf8, "café".to_owned(), false));
+        assert!(detected(b"caf\xe9") == (Encoding::Windows1252, "café".to_owned(), false));
+        assert!(detected(b"\xff\xfea\x00") == (Encoding::Utf16Le, "\u{feff}a".to_owned(), false));
+        assert!(detected(b"\xfe\xff\x00a") == (Encoding::Utf16Be, "\u{feff}a".to_owned(), false));
+        assert!(detected(b"\xef\xbb\xbfa") == (Encoding::Utf8, "\u{feff}a".to_owned(), false));
+        assert!(detected(b"ELF\x00\xff") == (Encoding::Latin1, "ELF\x00\u{ff}".to_owned(), true));
+        assert!(detected(b"") == (Encoding::Utf8, String::new(), false));
+        // A byte order mark followed by bytes that are not UTF-16 is not UTF-16.
+        assert!(!detected(b"\xff\xfea").2);
+        assert!(detected(b"\xff\xfea").0 == Encoding::Windows1252);
+    }
+}
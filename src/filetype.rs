@@ -0,0 +1,204 @@
+use std::path::Path;
+
+pub struct FileType {
+    name: String,
+    hl_opts: HighlightingOptions,
+}
+
+#[derive(Default)]
+pub struct HighlightingOptions {
+    numbers: bool,
+    characters: bool,
+    strings: &'static [char],
+    comment: Option<&'static str>,
+    multiline_comment: Option<(&'static str, &'static str)>,
+    multiline_string: Option<&'static str>,
+    heading: Option<char>,
+    primary_keywords: &'static [&'static str],
+    secondary_keywords: &'static [&'static str],
+}
+
+impl Default for FileType {
+    fn default() -> Self {
+        Self {
+            name: String::from("No filetype"),
+            hl_opts: HighlightingOptions::default(),
+        }
+    }
+}
+
+impl FileType {
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[must_use]
+    pub fn highlighting_options(&self) -> &HighlightingOptions {
+        &self.hl_opts
+    }
+
+    #[must_use]
+    pub fn from(file_name: &str) -> Self {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let (name, hl_opts) = match extension {
+            "rs" => ("Rust", rust()),
+            "c" | "h" => ("C", c()),
+            "py" => ("Python", python()),
+            "json" => ("JSON", json()),
+            "toml" => ("TOML", toml()),
+            "md" | "markdown" => ("Markdown", markdown()),
+            _ => return Self::default(),
+        };
+        Self {
+            name: String::from(name),
+            hl_opts,
+        }
+    }
+}
+
+impl HighlightingOptions {
+    #[must_use]
+    pub fn numbers(&self) -> bool {
+        self.numbers
+    }
+
+    #[must_use]
+    pub fn characters(&self) -> bool {
+        self.characters
+    }
+
+    #[must_use]
+    pub fn strings(&self) -> &[char] {
+        self.strings
+    }
+
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.comment
+    }
+
+    #[must_use]
+    pub fn multiline_comment(&self) -> Option<(&str, &str)> {
+        self.multiline_comment
+    }
+
+    #[must_use]
+    pub fn multiline_string(&self) -> Option<&str> {
+        self.multiline_string
+    }
+
+    #[must_use]
+    pub fn heading(&self) -> Option<char> {
+        self.heading
+    }
+
+    #[must_use]
+    pub fn primary_keywords(&self) -> &[&str] {
+        self.primary_keywords
+    }
+
+    #[must_use]
+    pub fn secondary_keywords(&self) -> &[&str] {
+        self.secondary_keywords
+    }
+}
+
+fn rust() -> HighlightingOptions {
+    HighlightingOptions {
+        numbers: true,
+        characters: true,
+        strings: &['"'],
+        comment: Some("//"),
+        multiline_comment: Some(("/*", "*/")),
+        primary_keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        secondary_keywords: &[
+            "bool", "char", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+            "u128", "usize", "f32", "f64", "str", "String", "Vec", "Option", "Result", "Box",
+            "Some", "None", "Ok", "Err",
+        ],
+        ..HighlightingOptions::default()
+    }
+}
+
+fn c() -> HighlightingOptions {
+    HighlightingOptions {
+        numbers: true,
+        characters: true,
+        strings: &['"'],
+        comment: Some("//"),
+        multiline_comment: Some(("/*", "*/")),
+        primary_keywords: &[
+            "auto", "break", "case", "const", "continue", "default", "do", "else", "enum",
+            "extern", "for", "goto", "if", "inline", "register", "restrict", "return", "sizeof",
+            "static", "struct", "switch", "typedef", "union", "volatile", "while", "#include",
+            "#define", "#undef", "#if", "#ifdef", "#ifndef", "#else", "#elif", "#endif",
+            "#pragma", "NULL",
+        ],
+        secondary_keywords: &[
+            "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void",
+            "bool", "size_t", "ssize_t", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t",
+            "uint16_t", "uint32_t", "uint64_t", "FILE",
+        ],
+        ..HighlightingOptions::default()
+    }
+}
+
+fn python() -> HighlightingOptions {
+    HighlightingOptions {
+        numbers: true,
+        strings: &['"', '\''],
+        comment: Some("#"),
+        multiline_string: Some("\"\"\""),
+        primary_keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        secondary_keywords: &[
+            "bool", "bytes", "dict", "float", "int", "list", "object", "self", "set", "str",
+            "tuple", "len", "print", "range", "type",
+        ],
+        ..HighlightingOptions::default()
+    }
+}
+
+fn json() -> HighlightingOptions {
+    HighlightingOptions {
+        numbers: true,
+        strings: &['"'],
+        primary_keywords: &["true", "false", "null"],
+        ..HighlightingOptions::default()
+    }
+}
+
+fn toml() -> HighlightingOptions {
+    HighlightingOptions {
+        numbers: true,
+        strings: &['"', '\''],
+        comment: Some("#"),
+        multiline_string: Some("\"\"\""),
+        heading: Some('['),
+        primary_keywords: &["true", "false"],
+        ..HighlightingOptions::default()
+    }
+}
+
+fn markdown() -> HighlightingOptions {
+    HighlightingOptions {
+        strings: &['`'],
+        multiline_comment: Some(("<!--", "-->")),
+        multiline_string: Some("```"),
+        heading: Some('#'),
+        ..HighlightingOptions::default()
+    }
+}
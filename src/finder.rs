@@ -0,0 +1,404 @@
+use super::{Encoding, FileType, Row};
+
+use regex::Regex;
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+// Files listed at most, so that huge trees stay responsive.
+const MAX_FILES: usize = 100_000;
+// Bytes read from a file to preview it.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+// Fuzzy match scores: a bonus for each matched character depending on what
+// precedes it, and a penalty for each character skipped between matches.
+const BONUS_CONSECUTIVE: i64 = 6;
+const BONUS_SEGMENT: i64 = 10;
+const BONUS_WORD: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const PENALTY_GAP: i64 = 1;
+const NO_MATCH: i64 = i64::MIN / 4;
+
+// A `.gitignore` pattern along with the directory of the file it came from.
+struct Rule {
+    // Relative to the root and ending in a slash, or empty for the root.
+    base: String,
+    pattern: Regex,
+    negated: bool,
+    directory_only: bool,
+}
+
+impl Rule {
+    fn parse(line: &str, base: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = line.strip_prefix('!').map_or((false, line), |line| (true, line));
+        // A backslash escapes a leading `#` or `!`.
+        let line = line.strip_prefix('\\').unwrap_or(line);
+        let (directory_only, line) = line.strip_suffix('/').map_or((false, line), |line| (true, line));
+        // Patterns with a slash other than at the end are relative to the
+        // directory of the `.gitignore`; others match at any depth.
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let pattern = format!("^{}{}$", if anchored { "" } else { "(?:.*/)?" }, translate(line));
+        Some(Self {
+            base: base.to_owned(),
+            pattern: Regex::new(&pattern).ok()?,
+            negated,
+            directory_only,
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        (is_dir || !self.directory_only)
+            && path
+                .strip_prefix(self.base.as_str())
+                .is_some_and(|path| self.pattern.is_match(path))
+    }
+}
+
+// Turns a glob as found in `.gitignore` into a regular expression.
+fn translate(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` matches any number of directories, none included.
+                if chars.next_if_eq(&'/').is_some() {
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            },
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' if chars.clone().any(|c| c == ']') => {
+                regex.push('[');
+                if chars.next_if(|&c| c == '!' || c == '^').is_some() {
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if matches!(c, '\\' | '[' | '&' | '~') {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            },
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+                }
+            },
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex
+}
+
+// Lists the files below `root` as sorted relative paths, leaving out hidden
+// files and directories, `target` directories and what `.gitignore` files
+// ignore.
+#[must_use]
+pub fn walk(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    visit(root, "", &mut Vec::new(), &mut files);
+    files.sort();
+    files
+}
+
+fn visit(root: &Path, directory: &str, rules: &mut Vec<Rule>, files: &mut Vec<String>) {
+    let path = root.join(directory);
+    let inherited = rules.len();
+    if let Ok(text) = fs::read_to_string(path.join(".gitignore")) {
+        rules.extend(text.lines().filter_map(|line| Rule::parse(line, directory)));
+    }
+    let mut entries: Vec<_> = fs::read_dir(&path)
+        .map(|entries| entries.filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        if files.len() >= MAX_FILES {
+            break;
+        }
+        let (Ok(name), Ok(file_type)) = (entry.file_name().into_string(), entry.file_type()) else {
+            continue;
+        };
+        let is_dir = file_type.is_dir();
+        if name.starts_with('.') || (is_dir && name == "target") {
+            continue;
+        }
+        let relative = format!("{}{}", directory, name);
+        // Deeper and later rules take precedence.
+        let ignored = rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(&relative, is_dir))
+            .is_some_and(|rule| !rule.negated);
+        if ignored {
+            continue;
+        }
+        if is_dir {
+            visit(root, &format!("{}/", relative), rules, files);
+        } else if entry.path().is_file() {
+            // Links to files are listed, but links to directories are not
+            // followed so that cycles cannot occur.
+            files.push(relative);
+        }
+    }
+    rules.truncate(inherited);
+}
+
+// A file listed by `walk`, with its path folded to lowercase once rather than
+// for every query.
+pub struct Candidate {
+    pub path: String,
+    folded: String,
+}
+
+impl From<String> for Candidate {
+    fn from(path: String) -> Self {
+        let folded = path.chars().map(fold).collect();
+        Self { path, folded }
+    }
+}
+
+// Lowercases `c` without changing the number of characters.
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// The characters of `query`, folded unless it has capitals, and whether it does.
+fn fold_query(query: &str) -> (Vec<char>, bool) {
+    let exact = query.chars().any(char::is_uppercase);
+    let query = query.chars().map(|c| if exact { c } else { fold(c) }).collect();
+    (query, exact)
+}
+
+// Scores how well `query` matches `candidate` with its characters in order
+// but not necessarily adjacent; higher is better. Matches right after a path
+// separator, a word boundary or at a capital count more, and gaps count
+// against. Case is ignored unless the query has capitals.
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+fn score(query: &[char], exact: bool, candidate: &Candidate) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text = if exact { &candidate.path } else { &candidate.folded };
+    let mut remaining = text.chars();
+    if !query.iter().all(|&q| remaining.any(|c| c == q)) {
+        return None;
+    }
+
+    let original: Vec<char> = candidate.path.chars().collect();
+    let chars: Vec<char> = text.chars().collect();
+    let bonus: Vec<i64> = (0..chars.len())
+        .map(|index| {
+            let Some(&previous) = index.checked_sub(1).and_then(|previous| original.get(previous)) else {
+                return BONUS_SEGMENT;
+            };
+            match previous {
+                '/' => BONUS_SEGMENT,
+                '_' | '-' | '.' | ' ' => BONUS_WORD,
+                _ if previous.is_lowercase() && original[index].is_uppercase() => BONUS_CAMEL,
+                _ => 0,
+            }
+        })
+        .collect();
+    // For the query so far, the best score with its last character matched
+    // exactly at each position, and the best with it matched anywhere up to
+    // each position.
+    let mut at = vec![NO_MATCH; chars.len()];
+    let mut upto = vec![NO_MATCH; chars.len()];
+    for (i, &q) in query.iter().enumerate() {
+        let mut row_at = vec![NO_MATCH; chars.len()];
+        let mut row_upto = vec![NO_MATCH; chars.len()];
+        let mut best = NO_MATCH;
+        for (j, &c) in chars.iter().enumerate() {
+            if c == q {
+                row_at[j] = if i == 0 {
+                    bonus[j] - PENALTY_GAP * j as i64
+                } else if j > 0 {
+                    (upto[j - 1] + bonus[j]).max(at[j - 1] + BONUS_CONSECUTIVE)
+                } else {
+                    NO_MATCH
+                };
+            }
+            best = (best - PENALTY_GAP).max(row_at[j]);
+            row_upto[j] = best;
+        }
+        at = row_at;
+        upto = row_upto;
+    }
+    upto.last().copied().filter(|&score| score > NO_MATCH / 2)
+}
+
+// The indices of the files matching `query`, best first.
+#[must_use]
+pub fn rank(query: &str, files: &[Candidate]) -> Vec<usize> {
+    let (query, exact) = fold_query(query);
+    let mut matches: Vec<(i64, usize)> = files
+        .iter()
+        .enumerate()
+        .filter_map(|(index, file)| score(&query, exact, file).map(|score| (score, index)))
+        .collect();
+    matches.sort_by_key(|&(score, index)| (Reverse(score), files.get(index).map_or(0, |file| file.path.len()), index));
+    matches.into_iter().map(|(_, index)| index).collect()
+}
+
+// The first `lines` lines of the file at `path`, highlighted, or a note saying
+// why it cannot be shown.
+#[must_use]
+pub fn preview(path: &str, lines: usize) -> Vec<Row> {
+    let mut bytes = Vec::new();
+    if let Err(error) = File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut bytes)) {
+        return vec![Row::from(format!("({})", error).as_str())];
+    }
+    // Leave out a character cut off at the end of what was read.
+    if let Err(error) = std::str::from_utf8(&bytes) {
+        if error.error_len().is_none() {
+            bytes.truncate(error.valid_up_to());
+        }
+    }
+    let decoded = Encoding::detect(&bytes);
+    if decoded.binary {
+        return vec![Row::from("(binary file)")];
+    }
+    let file_type = FileType::from(path);
+    let mut open = None;
+    decoded
+        .text
+        .lines()
+        .take(lines)
+        .map(|line| {
+            let mut row = Row::from(line);
+            open = row.highlight(file_type.highlighting_options(), open);
+            row
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether `path` is ignored by `lines` from a `.gitignore` in `base`,
+    // with later rules taking precedence as in `visit`.
+    fn ignored(lines: &[&str], base: &str, path: &str, is_dir: bool) -> bool {
+        let rules: Vec<Rule> = lines.iter().filter_map(|line| Rule::parse(line, base)).collect();
+        rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    fn ranked(query: &str, paths: &[&str]) -> Vec<String> {
+        let files: Vec<Candidate> = paths.iter().map(|&path| Candidate::from(path.to_owned())).collect();
+        rank(query, &files)
+            .into_iter()
+            .map(|index| files[index].path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        assert!(Rule::parse("", "").is_none());
+        assert!(Rule::parse("   ", "").is_none());
+        assert!(Rule::parse("# notes", "").is_none());
+        assert!(ignored(&["\\#notes"], "", "#notes", false));
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_at_any_depth() {
+        let lines = ["*.log"];
+        assert!(ignored(&lines, "", "debug.log", false));
+        assert!(ignored(&lines, "", "a/b/debug.log", false));
+        assert!(!ignored(&lines, "", "debug.log.txt", false));
+        // `*` does not cross directories.
+        assert!(!ignored(&["a*b"], "", "a/b", false));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored() {
+        assert!(ignored(&["/build"], "", "build", true));
+        assert!(!ignored(&["/build"], "", "src/build", true));
+        assert!(ignored(&["doc/out"], "", "doc/out", false));
+        assert!(!ignored(&["doc/out"], "", "sub/doc/out", false));
+    }
+
+    #[test]
+    fn rules_apply_below_their_directory() {
+        assert!(ignored(&["/out"], "sub/", "sub/out", false));
+        assert!(!ignored(&["/out"], "sub/", "out", false));
+        assert!(ignored(&["*.o"], "sub/", "sub/deep/x.o", false));
+        assert!(!ignored(&["*.o"], "sub/", "other/x.o", false));
+    }
+
+    #[test]
+    fn trailing_slash_matches_only_directories() {
+        assert!(ignored(&["cache/"], "", "cache", true));
+        assert!(ignored(&["cache/"], "", "a/cache", true));
+        assert!(!ignored(&["cache/"], "", "cache", false));
+    }
+
+    #[test]
+    fn negation_reincludes_paths() {
+        let lines = ["*.log", "!keep.log"];
+        assert!(ignored(&lines, "", "debug.log", false));
+        assert!(!ignored(&lines, "", "keep.log", false));
+        // A later rule wins over an earlier negation.
+        assert!(ignored(&["!keep.log", "*.log"], "", "keep.log", false));
+        assert!(ignored(&["\\!bang"], "", "!bang", false));
+    }
+
+    #[test]
+    fn double_star_matches_any_directories() {
+        let lines = ["a/**/b"];
+        assert!(ignored(&lines, "", "a/b", false));
+        assert!(ignored(&lines, "", "a/x/y/b", false));
+        assert!(!ignored(&lines, "", "c/a/b", false));
+        assert!(ignored(&["logs/**"], "", "logs/a/b.txt", false));
+    }
+
+    #[test]
+    fn translates_character_classes() {
+        assert!(ignored(&["file[0-9]"], "", "file3", false));
+        assert!(!ignored(&["file[!0-9]"], "", "file3", false));
+        assert!(ignored(&["file[!0-9]"], "", "filex", false));
+        assert!(ignored(&["?.txt"], "", "a.txt", false));
+        assert!(!ignored(&["?.txt"], "", "ab.txt", false));
+    }
+
+    #[test]
+    fn score_requires_characters_in_order() {
+        assert!(score(&['a', 'b'], false, &Candidate::from("xaxb".to_owned())).is_some());
+        assert!(score(&['b', 'a'], false, &Candidate::from("xaxb".to_owned())).is_none());
+        // An empty query keeps the order of the listing.
+        assert_eq!(ranked("", &["b", "a"]), ["b", "a"]);
+    }
+
+    #[test]
+    fn ranks_boundaries_and_runs_first() {
+        assert_eq!(ranked("ed", &["src/shed.rs", "src/editor.rs"]), ["src/editor.rs", "src/shed.rs"]);
+        assert_eq!(ranked("fb", &["foobar", "foo_bar"]), ["foo_bar", "foobar"]);
+        assert_eq!(ranked("fb", &["foobar", "fooBar"]), ["fooBar", "foobar"]);
+        assert_eq!(ranked("row", &["rxoxw.rs", "row.rs"]), ["row.rs", "rxoxw.rs"]);
+        // Ties go to the shorter path.
+        assert_eq!(ranked("a", &["a/b/c", "a/b"]), ["a/b", "a/b/c"]);
+    }
+
+    #[test]
+    fn ignores_case_unless_the_query_has_capitals() {
+        assert_eq!(ranked("readme", &["README.md", "src"]), ["README.md"]);
+        assert_eq!(ranked("Read", &["readme", "Readme"]), ["Readme"]);
+    }
+}
@@ -0,0 +1,48 @@
+use super::Position;
+
+use std::fmt::Write;
+use termion::{clear, cursor};
+
+// The lines currently on screen. Each refresh is compared against them so only
+// the lines that changed are sent to the terminal.
+#[derive(Default)]
+pub struct Frame {
+    lines: Vec<String>,
+}
+
+impl Frame {
+    // Forgets what is on screen, so the next frame is drawn in full.
+    pub fn invalidate(&mut self) {
+        self.lines.clear();
+    }
+
+    // Returns the output that turns the previous frame into `lines` and leaves
+    // the cursor at `cursor`, then remembers `lines` as being on screen.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn render(&mut self, lines: Vec<String>, cursor: &Position) -> String {
+        let mut output = cursor::Hide.to_string();
+        for (y, line) in lines.iter().enumerate() {
+            if self.lines.get(y) == Some(line) {
+                continue;
+            }
+            let _ = write!(
+                output,
+                "{}{}{}",
+                cursor::Goto(1, y.saturating_add(1) as u16),
+                clear::CurrentLine,
+                line
+            );
+        }
+        let _ = write!(
+            output,
+            "{}{}",
+            cursor::Goto(
+                cursor.x.saturating_add(1) as u16,
+                cursor.y.saturating_add(1) as u16
+            ),
+            cursor::Show
+        );
+        self.lines = lines;
+        output
+    }
+}
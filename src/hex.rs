@@ -0,0 +1,248 @@
+use std::fmt::Write;
+use termion::style;
+
+pub const BYTES_PER_LINE: usize = 16;
+// Width of the offset column and the gap after it.
+const OFFSET_WIDTH: usize = 10;
+
+// The raw bytes of a document while it is shown in hex. Edits overwrite bytes
+// in place, so the length never changes.
+pub struct HexBytes {
+    bytes: Vec<u8>,
+    // Offset and previous value of each edit, most recent last.
+    undo: Vec<(usize, u8)>,
+    redo: Vec<(usize, u8)>,
+    modified: bool,
+}
+
+impl HexBytes {
+    #[must_use]
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            modified: false,
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // Whether the bytes changed since they were last saved or read.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+    }
+
+    // Number of lines in the view; an empty buffer still shows one.
+    #[must_use]
+    pub fn lines(&self) -> usize {
+        self.bytes.len().div_ceil(BYTES_PER_LINE).max(1)
+    }
+
+    // Overwrites the high or low nibble of the byte at `offset`.
+    pub fn set_nibble(&mut self, offset: usize, high: bool, value: u8) {
+        let Some(byte) = self.bytes.get_mut(offset) else {
+            return;
+        };
+        let new = if high {
+            (*byte & 0x0f) | (value << 4)
+        } else {
+            (*byte & 0xf0) | (value & 0x0f)
+        };
+        if new != *byte {
+            self.undo.push((offset, *byte));
+            self.redo.clear();
+            *byte = new;
+            self.modified = true;
+        }
+    }
+
+    // Reverts the last edit and returns its offset.
+    pub fn undo(&mut self) -> Option<usize> {
+        let (offset, value) = self.undo.pop()?;
+        let byte = self.bytes.get_mut(offset)?;
+        self.redo.push((offset, *byte));
+        *byte = value;
+        Some(offset)
+    }
+
+    pub fn redo(&mut self) -> Option<usize> {
+        let (offset, value) = self.redo.pop()?;
+        let byte = self.bytes.get_mut(offset)?;
+        self.undo.push((offset, *byte));
+        *byte = value;
+        Some(offset)
+    }
+
+    // Finds `needle` starting at or after `from`.
+    #[must_use]
+    pub fn find(&self, needle: &[u8], from: usize) -> Option<usize> {
+        if needle.is_empty() {
+            return None;
+        }
+        self.bytes
+            .get(from..)?
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|position| position.saturating_add(from))
+    }
+
+    // Renders line `line` as offset, hex bytes and ASCII columns, inverting the
+    // ASCII character of the byte at `cursor`.
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn render_line(&self, line: usize, cursor: usize) -> String {
+        let start = line * BYTES_PER_LINE;
+        let bytes = self
+            .bytes
+            .get(start..self.bytes.len().min(start + BYTES_PER_LINE))
+            .unwrap_or_default();
+        let mut result = format!("{:08x}  ", start);
+        for index in 0..BYTES_PER_LINE {
+            match bytes.get(index) {
+                Some(byte) => {
+                    let _ = write!(result, "{:02x} ", byte);
+                },
+                None => result.push_str("   "),
+            }
+            if index == BYTES_PER_LINE / 2 - 1 {
+                result.push(' ');
+            }
+        }
+        result.push_str(" |");
+        for (index, &byte) in bytes.iter().enumerate() {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                char::from(byte)
+            } else {
+                '.'
+            };
+            if start + index == cursor {
+                let _ = write!(result, "{}{}{}", style::Invert, c, style::NoInvert);
+            } else {
+                result.push(c);
+            }
+        }
+        result.push('|');
+        result
+    }
+}
+
+// The screen column of nibble `nibble` (0 to 31) within a line.
+#[must_use]
+#[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+pub fn column(nibble: usize) -> usize {
+    let byte = nibble / 2;
+    let gap = usize::from(byte >= BYTES_PER_LINE / 2);
+    OFFSET_WIDTH + byte * 3 + gap + nibble % 2
+}
+
+// Parses hex digits such as `7f 45 4c 46` or `7f454c46` into bytes.
+#[must_use]
+pub fn parse(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).and_then(|digit| u8::try_from(digit).ok()))
+        .collect::<Option<_>>()?;
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    Some(
+        digits
+            .chunks_exact(2)
+            .map(|pair| {
+                let high = pair.first().copied().unwrap_or(0);
+                let low = pair.get(1).copied().unwrap_or(0);
+                (high << 4) | low
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_pairs_of_hex_digits() {
+        assert_eq!(parse("7f 45 4c 46"), Some(vec![0x7f, 0x45, 0x4c, 0x46]));
+        assert_eq!(parse("7F454c46"), Some(vec![0x7f, 0x45, 0x4c, 0x46]));
+        assert_eq!(parse(" 0 0\tff "), Some(vec![0x00, 0xff]));
+        // Odd digits, invalid nibbles and empty input are rejected.
+        assert_eq!(parse("7f4"), None);
+        assert_eq!(parse("zz"), None);
+        assert_eq!(parse("0x7f"), None);
+        assert_eq!(parse("٣٣"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("   "), None);
+    }
+
+    #[test]
+    fn nibbles_are_overwritten_and_undone() {
+        let mut hex = HexBytes::new(vec![0x12, 0x34]);
+        hex.set_nibble(0, true, 0xa);
+        hex.set_nibble(1, false, 0xb);
+        assert_eq!(hex.as_bytes(), [0xa2, 0x3b]);
+        assert!(hex.is_modified());
+        // Writing the same value records nothing.
+        hex.set_nibble(1, false, 0xb);
+        // Offsets past the end are ignored.
+        hex.set_nibble(2, true, 0xf);
+        assert_eq!(hex.len(), 2);
+
+        assert_eq!(hex.undo(), Some(1));
+        assert_eq!(hex.undo(), Some(0));
+        assert_eq!(hex.undo(), None);
+        assert_eq!(hex.as_bytes(), [0x12, 0x34]);
+        assert_eq!(hex.redo(), Some(0));
+        assert_eq!(hex.as_bytes(), [0xa2, 0x34]);
+
+        hex.mark_saved();
+        assert!(!hex.is_modified());
+    }
+
+    #[test]
+    fn find_looks_from_an_offset() {
+        let hex = HexBytes::new(b"abcabc".to_vec());
+        assert_eq!(hex.find(b"bc", 0), Some(1));
+        assert_eq!(hex.find(b"bc", 2), Some(4));
+        assert_eq!(hex.find(b"bc", 5), None);
+        assert_eq!(hex.find(b"", 0), None);
+        assert_eq!(hex.find(b"a", 9), None);
+    }
+
+    #[test]
+    fn lines_show_offset_bytes_and_text() {
+        let hex = HexBytes::new((0x41..0x55).collect());
+        assert_eq!(hex.lines(), 2);
+        assert_eq!(HexBytes::new(Vec::new()).lines(), 1);
+        assert_eq!(
+            hex.render_line(1, 0),
+            format!("00000010  51 52 53 54 {:37} |QRST|", "")
+        );
+        let first = hex.render_line(0, 1);
+        assert!(first.starts_with("00000000  41 42 43 44 45 46 47 48  49 4a"));
+        assert!(first.ends_with(&format!("|A{}B{}CDEFGHIJKLMNOP|", style::Invert, style::NoInvert)));
+        assert_eq!(column(0), 10);
+        assert_eq!(column(1), 11);
+        assert_eq!(column(16), 10 + 8 * 3 + 1);
+    }
+}
@@ -0,0 +1,41 @@
+use termion::color;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    None,
+    Number,
+    String,
+    Character,
+    Comment,
+    MultilineComment,
+    PrimaryKeywords,
+    SecondaryKeywords,
+    Match,
+    CurrentMatch,
+    Selection,
+}
+
+impl Type {
+    #[must_use]
+    pub fn to_color(self) -> Option<color::Rgb> {
+        match self {
+            Type::None | Type::Selection => None,
+            Type::Number => Some(color::Rgb(220, 163, 163)),
+            Type::String => Some(color::Rgb(211, 54, 130)),
+            Type::Character => Some(color::Rgb(108, 113, 196)),
+            Type::Comment | Type::MultilineComment => Some(color::Rgb(133, 153, 0)),
+            Type::PrimaryKeywords => Some(color::Rgb(181, 137, 0)),
+            Type::SecondaryKeywords => Some(color::Rgb(42, 161, 152)),
+            Type::Match | Type::CurrentMatch => Some(color::Rgb(0, 0, 0)),
+        }
+    }
+
+    #[must_use]
+    pub fn to_background(self) -> Option<color::Rgb> {
+        match self {
+            Type::Match => Some(color::Rgb(38, 139, 210)),
+            Type::CurrentMatch => Some(color::Rgb(203, 75, 22)),
+            _ => None,
+        }
+    }
+}
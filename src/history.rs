@@ -0,0 +1,117 @@
+use super::Position;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Delete,
+}
+
+#[derive(Clone)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub start: Position,
+    pub end: Position,
+    pub text: String,
+    pub cursor_before: Position,
+    pub cursor_after: Position,
+    // Undone and redone together with the change recorded before it.
+    pub joined: bool,
+    // An insert on the line after the last one, which adds that line.
+    pub appended: bool,
+}
+
+impl Change {
+    // Typed characters are merged into the previous insert as long as they
+    // continue where it left off and do not start a new line.
+    fn extends(&self, next: &Self) -> bool {
+        self.kind == ChangeKind::Insert
+            && next.kind == ChangeKind::Insert
+            && self.end == next.start
+            && !self.text.ends_with('\n')
+            && !next.text.contains('\n')
+            && !next.joined
+            && !next.appended
+    }
+}
+
+pub struct History {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    saved: Option<usize>,
+    coalesce: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: Some(0),
+            coalesce: true,
+        }
+    }
+}
+
+impl History {
+    pub fn record(&mut self, change: Change) {
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        self.redo.clear();
+
+        let at_saved = self.saved == Some(self.undo.len());
+        if let Some(last) = self.undo.last_mut() {
+            if self.coalesce && !at_saved && last.extends(&change) {
+                last.end = change.end;
+                last.text.push_str(&change.text);
+                last.cursor_after = change.cursor_after;
+                return;
+            }
+        }
+        self.coalesce = true;
+        self.undo.push(change);
+    }
+
+    // Prevents the next recorded change from being merged into the last one.
+    pub fn seal(&mut self) {
+        self.coalesce = false;
+    }
+
+    // Returns the changes of the last step, most recent first.
+    pub fn undo(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        while let Some(change) = self.undo.pop() {
+            let joined = change.joined;
+            self.redo.push(change.clone());
+            changes.push(change);
+            if !joined {
+                break;
+            }
+        }
+        self.coalesce = false;
+        changes
+    }
+
+    // Returns the changes of the next undone step in the order they were made.
+    pub fn redo(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        while let Some(change) = self.redo.pop() {
+            self.undo.push(change.clone());
+            changes.push(change);
+            if !self.redo.last().is_some_and(|next| next.joined) {
+                break;
+            }
+        }
+        self.coalesce = false;
+        changes
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+    }
+
+    #[must_use]
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.undo.len())
+    }
+}
@@ -0,0 +1,35 @@
+mod atomic;
+mod clipboard;
+mod completion;
+mod config;
+mod directory;
+mod document;
+mod editor;
+mod encoding;
+mod filetype;
+mod finder;
+mod frame;
+mod hex;
+mod highlighting;
+mod history;
+mod rope;
+mod row;
+mod search;
+mod swap;
+mod terminal;
+mod virtual_terminal;
+mod window;
+
+pub use config::{Config, LineNumbers};
+pub use editor::Editor;
+pub use terminal::{Backend, Event, Size, Terminal};
+pub use virtual_terminal::{Cell, VirtualTerminal};
+pub use editor::Position;
+pub use document::Document;
+pub use encoding::Encoding;
+pub use filetype::{FileType, HighlightingOptions};
+pub use hex::HexBytes;
+pub use history::History;
+pub use rope::Rope;
+pub use row::{LineEnding, Row};
+pub use search::{Query, SearchOptions};
@@ -1,13 +1,4 @@
-mod document;
-mod editor;
-mod row;
-mod terminal;
-
-use editor::Editor;
-pub use terminal::Terminal;
-pub use editor::Position;
-pub use document::Document;
-pub use row::Row;
+use hecto::Editor;
 
 fn main() {
    Editor::default().run();
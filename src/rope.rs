@@ -0,0 +1,341 @@
+use super::Row;
+
+use std::mem;
+use std::ops::Range;
+
+// A sequence of rows kept in a randomized binary tree ordered by position, so
+// indexing, inserting and removing rows are all O(log n) regardless of the
+// document size. The tree holds whole rows; edits within a row are up to `Row`.
+pub struct Rope {
+    root: Option<Box<Node>>,
+    seed: u64,
+}
+
+struct Node {
+    row: Row,
+    size: usize,
+    left: Option<Box<Node>>,
+    right: Option<Box<Node>>,
+}
+
+impl Node {
+    fn new(row: Row) -> Box<Self> {
+        Box::new(Self {
+            row,
+            size: 1,
+            left: None,
+            right: None,
+        })
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+fn size(node: &Option<Box<Node>>) -> usize {
+    node.as_ref().map_or(0, |node| node.size)
+}
+
+impl Default for Rope {
+    fn default() -> Self {
+        Self {
+            root: None,
+            seed: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+}
+
+impl From<Vec<Row>> for Rope {
+    fn from(rows: Vec<Row>) -> Self {
+        let len = rows.len();
+        let mut rows = rows.into_iter();
+        Self {
+            root: build(&mut rows, len),
+            ..Self::default()
+        }
+    }
+}
+
+// Builds a perfectly balanced tree from the next `len` rows in order.
+#[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+fn build(rows: &mut impl Iterator<Item = Row>, len: usize) -> Option<Box<Node>> {
+    if len == 0 {
+        return None;
+    }
+    let left = build(rows, len / 2);
+    let mut node = Node::new(rows.next()?);
+    node.left = left;
+    node.right = build(rows, len - len / 2 - 1);
+    node.update();
+    Some(node)
+}
+
+impl Rope {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn get(&self, mut index: usize) -> Option<&Row> {
+        let mut node = self.root.as_deref()?;
+        loop {
+            let left = size(&node.left);
+            if index < left {
+                node = node.left.as_deref()?;
+            } else if index == left {
+                return Some(&node.row);
+            } else {
+                index -= left + 1;
+                node = node.right.as_deref()?;
+            }
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn get_mut(&mut self, mut index: usize) -> Option<&mut Row> {
+        let mut node = self.root.as_deref_mut()?;
+        loop {
+            let left = size(&node.left);
+            if index < left {
+                node = node.left.as_deref_mut()?;
+            } else if index == left {
+                return Some(&mut node.row);
+            } else {
+                index -= left + 1;
+                node = node.right.as_deref_mut()?;
+            }
+        }
+    }
+
+    pub fn push(&mut self, row: Row) {
+        self.insert(self.len(), row);
+    }
+
+    pub fn insert(&mut self, index: usize, row: Row) {
+        let (left, right) = split(self.root.take(), index);
+        let left = self.merge(left, Some(Node::new(row)));
+        self.root = self.merge(left, right);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Row> {
+        #[allow(clippy::arithmetic_side_effects)]
+        let mut removed = self.drain(index..index + 1);
+        removed.pop()
+    }
+
+    pub fn drain(&mut self, range: Range<usize>) -> Vec<Row> {
+        let (left, rest) = split(self.root.take(), range.start);
+        let (middle, right) = split(rest, range.end.saturating_sub(range.start));
+        self.root = self.merge(left, right);
+
+        let mut rows = Vec::with_capacity(size(&middle));
+        collect(middle, &mut rows);
+        rows
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_> {
+        self.iter_from(0)
+    }
+
+    // Iterates over the rows starting at `index` without walking the ones before it.
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn iter_from(&self, mut index: usize) -> Iter<'_> {
+        let mut stack = Vec::new();
+        let mut node = self.root.as_deref();
+        while let Some(current) = node {
+            let left = size(&current.left);
+            if index < left {
+                stack.push(current);
+                node = current.left.as_deref();
+            } else if index == left {
+                stack.push(current);
+                break;
+            } else {
+                index -= left + 1;
+                node = current.right.as_deref();
+            }
+        }
+        Iter { stack }
+    }
+
+    fn random(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    // Joins two trees, picking the root with a probability proportional to the
+    // size of each side so the result stays balanced in expectation.
+    #[allow(clippy::arithmetic_side_effects, clippy::cast_possible_truncation)]
+    fn merge(&mut self, left: Option<Box<Node>>, right: Option<Box<Node>>) -> Option<Box<Node>> {
+        match (left, right) {
+            (None, node) | (node, None) => node,
+            (Some(mut left), Some(mut right)) => {
+                let total = (left.size + right.size) as u64;
+                if self.random() % total < left.size as u64 {
+                    let child = left.right.take();
+                    left.right = self.merge(child, Some(right));
+                    left.update();
+                    Some(left)
+                } else {
+                    let child = right.left.take();
+                    right.left = self.merge(Some(left), child);
+                    right.update();
+                    Some(right)
+                }
+            }
+        }
+    }
+}
+
+// Splits a tree into its first `index` rows and the rest.
+#[allow(clippy::arithmetic_side_effects)]
+fn split(node: Option<Box<Node>>, index: usize) -> (Option<Box<Node>>, Option<Box<Node>>) {
+    let Some(mut node) = node else {
+        return (None, None);
+    };
+    let left = size(&node.left);
+    if index <= left {
+        let (first, second) = split(node.left.take(), index);
+        node.left = second;
+        node.update();
+        (first, Some(node))
+    } else {
+        let (first, second) = split(node.right.take(), index - left - 1);
+        node.right = first;
+        node.update();
+        (Some(node), second)
+    }
+}
+
+fn collect(node: Option<Box<Node>>, rows: &mut Vec<Row>) {
+    if let Some(mut node) = node {
+        collect(node.left.take(), rows);
+        let right = node.right.take();
+        rows.push(mem::take(&mut node.row));
+        collect(right, rows);
+    }
+}
+
+pub struct Iter<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Row;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        let mut next = node.right.as_deref();
+        while let Some(current) = next {
+            self.stack.push(current);
+            next = current.left.as_deref();
+        }
+        Some(&node.row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rope(len: usize) -> Rope {
+        Rope::from((0..len).map(|index| Row::from(index.to_string().as_str())).collect::<Vec<_>>())
+    }
+
+    fn texts<'a>(rows: impl Iterator<Item = &'a Row>) -> Vec<String> {
+        rows.map(|row| row.text(0..row.len()).to_owned()).collect()
+    }
+
+    // Checks that every node counts the rows below it and returns the depth.
+    fn check(node: &Option<Box<Node>>) -> usize {
+        let Some(node) = node else {
+            return 0;
+        };
+        let depth = check(&node.left).max(check(&node.right));
+        assert_eq!(node.size, 1 + size(&node.left) + size(&node.right));
+        depth + 1
+    }
+
+    #[test]
+    fn builds_a_balanced_tree_in_order() {
+        let rope = rope(1000);
+        assert_eq!(rope.len(), 1000);
+        assert_eq!(check(&rope.root), 10);
+        assert_eq!(texts(rope.iter()), texts((0..1000).map(|index| rope.get(index).unwrap())));
+        assert!(rope.get(1000).is_none());
+        assert!(Rope::default().is_empty());
+    }
+
+    #[test]
+    fn split_keeps_the_rows_on_each_side() {
+        for index in [0, 1, 6, 7] {
+            let mut rope = rope(7);
+            let (left, right) = split(rope.root.take(), index);
+            assert_eq!(size(&left), index.min(7));
+            assert_eq!(size(&right), 7 - index.min(7));
+            check(&left);
+            check(&right);
+            rope.root = rope.merge(left, right);
+            assert_eq!(texts(rope.iter()), ["0", "1", "2", "3", "4", "5", "6"]);
+        }
+    }
+
+    #[test]
+    fn insert_and_drain_match_a_vector() {
+        let mut rope = Rope::default();
+        let mut expected: Vec<String> = Vec::new();
+        let mut seed = 7_usize;
+        for step in 0..2000 {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            let at = (seed >> 33) % (expected.len() + 1);
+            if step % 3 == 2 && !expected.is_empty() {
+                let end = (at + (seed >> 40) % 4).min(expected.len());
+                let removed = rope.drain(at..end);
+                assert_eq!(texts(removed.iter()), expected.drain(at..end).collect::<Vec<_>>());
+            } else {
+                rope.insert(at, Row::from(step.to_string().as_str()));
+                expected.insert(at, step.to_string());
+            }
+        }
+        assert_eq!(rope.len(), expected.len());
+        assert_eq!(texts(rope.iter()), expected);
+        // Merging at random keeps the tree shallow in expectation.
+        assert!(check(&rope.root) < 40);
+    }
+
+    #[test]
+    fn remove_and_push_at_the_ends() {
+        let mut rope = rope(3);
+        assert_eq!(rope.remove(0).map(|row| row.text(0..1).to_owned()), Some("0".to_owned()));
+        assert!(rope.remove(5).is_none());
+        rope.push(Row::from("3"));
+        assert_eq!(texts(rope.iter()), ["1", "2", "3"]);
+        assert_eq!(rope.drain(2..9).len(), 1);
+        assert!(rope.drain(5..9).is_empty());
+        assert_eq!(texts(rope.iter()), ["1", "2"]);
+    }
+
+    #[test]
+    fn iter_from_starts_at_any_row() {
+        let rope = rope(50);
+        for index in 0..=50 {
+            let expected: Vec<String> = (index..50).map(|index| index.to_string()).collect();
+            assert_eq!(texts(rope.iter_from(index)), expected);
+        }
+        assert_eq!(rope.iter_from(80).count(), 0);
+        assert_eq!(Rope::default().iter().count(), 0);
+    }
+}
@@ -1,39 +1,142 @@
+use crate::filetype::HighlightingOptions;
+use crate::highlighting;
+use crate::search::Query;
+
 use std::cmp;
+use std::fmt::Write;
+use std::ops::Range;
+use termion::{color, style};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+}
 
+// A line of text, kept as one string. Edits within it move the bytes after
+// the edit and shift their grapheme offsets rather than re-segmenting the
+// line, but they still take time linear in its length.
 #[derive(Default)]
 pub struct Row {
     string: String,
+    // Byte offset of every grapheme, or `None` while the row is plain ASCII and
+    // grapheme indices are byte indices.
+    graphemes: Option<Vec<usize>>,
     len: usize,
+    highlighting: Vec<highlighting::Type>,
+    // The multi-line construct still open at the end of this row.
+    open: Option<highlighting::Type>,
+    // The line break written after this row.
+    ending: LineEnding,
 }
 
 impl From<&str> for Row {
     fn from(slice: &str) -> Self {
-        Self {
+        let mut row = Self {
             string: String::from(slice),
-            len: slice.graphemes(true).count(),
-        }
+            graphemes: None,
+            len: 0,
+            highlighting: Vec::new(),
+            open: None,
+            ending: LineEnding::default(),
+        };
+        row.reindex();
+        row
     }
 }
 
 impl Row {
+    // Renders the render columns `start..end`, with tab stops every `tab_width`
+    // columns; `overlay` spans of graphemes (such as search matches) take
+    // precedence over the row's own highlighting. Wide graphemes cut by either
+    // edge are drawn as blanks rather than split.
     #[must_use]
-    #[allow(clippy::string_slice)]
-    pub fn render(&self, start: usize, end: usize) -> String {
-        let end = cmp::min(end, self.string.len());
-        let start = cmp::min(start, end);
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn render(
+        &self,
+        start: usize,
+        end: usize,
+        tab_width: usize,
+        overlay: &[(Range<usize>, highlighting::Type)],
+    ) -> String {
         let mut result = String::new();
-        #[allow(clippy::integer_arithmetic)]
-        for grapheme in self.string[..]
-            .graphemes(true)
-            .skip(start)
-            .take(end - start)
-        {
-            if grapheme == "\t" {
-                result.push(' ');
+        let mut current = highlighting::Type::None;
+        // When every grapheme is one column, the ones before `start` can be
+        // skipped outright.
+        let first = if self.is_uniform() { cmp::min(start, self.len) } else { 0 };
+        let mut column = first;
+        for (index, grapheme) in self.text(first..self.len).graphemes(true).enumerate() {
+            if column >= end {
+                break;
+            }
+            let next = column + grapheme_width(grapheme, column, tab_width);
+            if next <= start {
+                column = next;
+                continue;
+            }
+            let index = first + index;
+            let kind = overlay
+                .iter()
+                .rev()
+                .find(|(range, _)| range.contains(&index))
+                .map(|&(_, kind)| kind)
+                .or_else(|| self.highlighting.get(index).copied())
+                .unwrap_or(highlighting::Type::None);
+            if kind != current {
+                current = kind;
+                let _ = match kind.to_color() {
+                    Some(highlight_color) => write!(result, "{}", color::Fg(highlight_color)),
+                    None => write!(result, "{}", color::Fg(color::Reset)),
+                };
+                let _ = match kind.to_background() {
+                    Some(background) => write!(result, "{}", color::Bg(background)),
+                    None => write!(result, "{}", color::Bg(color::Reset)),
+                };
+                let _ = if kind == highlighting::Type::Selection {
+                    write!(result, "{}", style::Invert)
+                } else {
+                    write!(result, "{}", style::NoInvert)
+                };
+            }
+            if grapheme == "\t" || column < start || next > end {
+                let visible = cmp::min(next, end) - cmp::max(column, start);
+                result.push_str(&" ".repeat(visible));
+            } else if let Some(symbol) = control_symbol(grapheme) {
+                result.push_str(&symbol);
             } else {
                 result.push_str(grapheme);
             }
+            column = next;
+        }
+        if current != highlighting::Type::None {
+            let _ = write!(
+                result,
+                "{}{}{}",
+                color::Fg(color::Reset),
+                color::Bg(color::Reset),
+                style::NoInvert
+            );
         }
 
         result
@@ -44,95 +147,511 @@ impl Row {
         self.len
     }
 
+    // The render column at which grapheme `index` starts.
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn column(&self, index: usize, tab_width: usize) -> usize {
+        let index = cmp::min(index, self.len);
+        if self.is_uniform() {
+            return index;
+        }
+        self.text(0..index)
+            .graphemes(true)
+            .fold(0, |column, grapheme| column + grapheme_width(grapheme, column, tab_width))
+    }
+
+    // The grapheme drawn at render column `column`, or the row length past its end.
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn index_at_column(&self, column: usize, tab_width: usize) -> usize {
+        if self.is_uniform() {
+            return cmp::min(column, self.len);
+        }
+        let mut next = 0;
+        for (index, grapheme) in self.string.graphemes(true).enumerate() {
+            next += grapheme_width(grapheme, next, tab_width);
+            if next > column {
+                return index;
+            }
+        }
+        self.len
+    }
+
     #[must_use]
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
 
-    #[allow(clippy::integer_arithmetic, clippy::string_slice)]
     pub fn insert(&mut self, pos: usize, c: char) {
-        if pos >= self.len() {
-            self.string.push(c);
-            self.len += 1;
-            return;
-        }
-        let mut result: String = String::new();
-        let mut length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            length += 1;
-            if index == pos {
-                length += 1;
-                result.push(c);
-            }
-            result.push_str(grapheme);
+        self.insert_str(pos, c.encode_utf8(&mut [0; 4]));
+    }
+
+    // Inserts `text` before grapheme `pos` and returns the graphemes now
+    // holding it, which include a neighbour it combined with.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn insert_str(&mut self, pos: usize, text: &str) -> Range<usize> {
+        let pos = cmp::min(pos, self.len);
+        let byte = self.byte_index(pos);
+        self.splice(pos, pos, text);
+        if text.is_empty() {
+            return pos..pos;
         }
-        self.len = length;
-        self.string = result;
+        self.grapheme_index(byte)..self.grapheme_index(byte + text.len() - 1) + 1
     }
 
-    #[allow(clippy::integer_arithmetic, clippy::string_slice)]
+    #[allow(clippy::arithmetic_side_effects)]
     pub fn delete(&mut self, pos: usize) {
         if pos >= self.len() {
             return;
         }
-        let mut result: String = String::new();
-        let mut length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            if index != pos {
-                length += 1;
-                result.push_str(grapheme);
-            }
-        }
-        self.len = length;
-        self.string = result;
+        self.splice(pos, pos + 1, "");
+    }
+
+    // Returns the text of the graphemes in `range`.
+    #[must_use]
+    #[allow(clippy::string_slice)]
+    pub fn text(&self, range: Range<usize>) -> &str {
+        let end = cmp::min(range.end, self.len);
+        let start = cmp::min(range.start, end);
+        &self.string[self.byte_index(start)..self.byte_index(end)]
     }
 
-    #[allow(clippy::integer_arithmetic)]
+    // Removes the graphemes in `range` and returns them.
+    #[must_use]
+    pub fn remove(&mut self, range: Range<usize>) -> String {
+        let end = cmp::min(range.end, self.len);
+        let start = cmp::min(range.start, end);
+        let removed = self.text(start..end).to_owned();
+        self.splice(start, end, "");
+        removed
+    }
+
+    // Joins `new` onto the end of this row, which then ends with its line break.
     pub fn append(&mut self, new: &Self) {
-        self.string = format!("{}{}", self.string, new.string);
-        self.len += new.len;
+        self.splice(self.len, self.len, &new.string);
+        self.ending = new.ending;
     }
 
     #[must_use]
-    #[allow(clippy::integer_arithmetic, clippy::string_slice)]
+    #[allow(clippy::arithmetic_side_effects)]
     pub fn split(&mut self, pos: usize) -> Self {
-        let mut row: String = String::new();
-        let mut length = 0;
-        let mut split_row: String = String::new();
-        let mut split_length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            if index < pos {
-                length += 1;
-                row.push_str(grapheme);
-            } else {
-                split_length += 1;
-                split_row.push_str(grapheme);
+        let pos = cmp::min(pos, self.len);
+        let byte = self.byte_index(pos);
+        let string = self.string.split_off(byte);
+        let len = self.len - pos;
+        let graphemes = self.graphemes.as_mut().map(|graphemes| {
+            let mut split = graphemes.split_off(pos);
+            for index in &mut split {
+                *index -= byte;
             }
-        }
-
-        self.string = row;
-        self.len = length;
+            split
+        });
+        self.len = pos;
         Self {
-            string: split_row,
-            len: split_length,
+            string,
+            graphemes,
+            len,
+            highlighting: Vec::new(),
+            open: None,
+            ending: self.ending,
         }
     }
 
+    #[must_use]
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    pub fn set_ending(&mut self, ending: LineEnding) {
+        self.ending = ending;
+    }
+
     #[must_use]
     pub fn as_bytes(&self) -> &[u8] {
         self.string.as_bytes()
     }
 
-    pub fn find(&self, query: &str, after: usize) -> Option<usize> {
-        let substring: String = self.string[..].graphemes(true).skip(after).collect();
-        let matching_byte_index = substring.find(query);
-        if let Some(matching_byte_index) = matching_byte_index {
-            for (grapheme_index, (byte_index, _)) in substring[..].grapheme_indices(true).enumerate() {
-                if matching_byte_index == byte_index {
-                    return Some(after + grapheme_index);
+    // Finds the first match at or after grapheme `after` and returns its grapheme
+    // index and length in graphemes.
+    #[must_use]
+    pub fn find(&self, query: &Query, after: usize) -> Option<(usize, usize)> {
+        if after > self.len {
+            return None;
+        }
+        let found = query.find_at(&self.string, self.byte_index(after))?;
+        Some(self.match_range(&found))
+    }
+
+    // The matches in the row as grapheme index and length, each one found after
+    // the previous one ends.
+    pub fn matches<'a>(&'a self, query: &'a Query) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let mut x = 0;
+        std::iter::from_fn(move || {
+            let (start, len) = self.find(query, x)?;
+            x = start.saturating_add(len.max(1));
+            Some((start, len))
+        })
+    }
+
+    // Finds the last match starting before grapheme `before`, taken from the
+    // same matches as `matches` so that searching back and forth agree.
+    #[must_use]
+    pub fn find_backward(&self, query: &Query, before: usize) -> Option<(usize, usize)> {
+        self.matches(query).take_while(|&(start, _)| start < before).last()
+    }
+
+    fn match_range(&self, found: &Range<usize>) -> (usize, usize) {
+        let start = self.grapheme_index(found.start);
+        if found.is_empty() {
+            return (start, 0);
+        }
+        // A match ending inside a grapheme still covers the whole grapheme.
+        let end = self.grapheme_index(found.end.saturating_sub(1)).saturating_add(1);
+        (start, end.saturating_sub(start))
+    }
+
+    // Highlights the row given the construct left open by the previous row and
+    // returns the one left open at its end.
+    #[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing, clippy::string_slice)]
+    pub fn highlight(
+        &mut self,
+        opts: &HighlightingOptions,
+        open: Option<highlighting::Type>,
+    ) -> Option<highlighting::Type> {
+        let graphemes: Vec<(usize, &str)> = self.string.grapheme_indices(true).collect();
+        let mut highlighting = Vec::with_capacity(graphemes.len());
+        let mut open = open;
+
+        if open.is_none() && opts.heading().is_some_and(|prefix| self.string.starts_with(prefix)) {
+            highlighting.resize(graphemes.len(), highlighting::Type::PrimaryKeywords);
+        }
+        let mut index = highlighting.len();
+        while let Some(&(byte, grapheme)) = graphemes.get(index) {
+            let rest = &self.string[byte..];
+            let follows_separator = index == 0 || is_separator(graphemes[index - 1].1);
+            let previous = highlighting.last().copied().unwrap_or(highlighting::Type::None);
+
+            let (kind, len) = if let Some(kind) = open {
+                let closing = match kind {
+                    highlighting::Type::String => opts.multiline_string(),
+                    _ => opts.multiline_comment().map(|(_, end)| end),
                 }
+                .unwrap_or_default();
+                if !closing.is_empty() && rest.starts_with(closing) {
+                    open = None;
+                    (kind, closing.len())
+                } else {
+                    (kind, 1)
+                }
+            } else if opts.comment().is_some_and(|prefix| rest.starts_with(prefix)) {
+                (highlighting::Type::Comment, graphemes.len() - index)
+            } else if let Some((start, _)) = opts.multiline_comment().filter(|(start, _)| rest.starts_with(start)) {
+                open = Some(highlighting::Type::MultilineComment);
+                (highlighting::Type::MultilineComment, start.len())
+            } else if let Some(delimiter) = opts.multiline_string().filter(|delimiter| rest.starts_with(delimiter)) {
+                open = Some(highlighting::Type::String);
+                (highlighting::Type::String, delimiter.len())
+            } else if let Some(len) = character_len(opts, &graphemes, index) {
+                (highlighting::Type::Character, len)
+            } else if opts.strings().iter().any(|&delimiter| rest.starts_with(delimiter)) {
+                (highlighting::Type::String, string_len(&graphemes, index))
+            } else if opts.numbers() && is_number(grapheme, previous, follows_separator) {
+                (highlighting::Type::Number, 1)
+            } else if let Some((kind, len)) = keyword_len(opts, rest).filter(|_| follows_separator) {
+                (kind, len)
+            } else {
+                (highlighting::Type::None, 1)
+            };
+
+            let len = len.clamp(1, graphemes.len() - index);
+            highlighting.resize(highlighting.len() + len, kind);
+            index += len;
+        }
+
+        self.highlighting = highlighting;
+        self.open = open;
+        open
+    }
+
+    #[must_use]
+    pub fn open_highlight(&self) -> Option<highlighting::Type> {
+        self.open
+    }
+
+    // Whether every grapheme takes exactly one column.
+    fn is_uniform(&self) -> bool {
+        self.graphemes.is_none() && !self.string.bytes().any(|byte| byte.is_ascii_control())
+    }
+
+    #[allow(clippy::indexing_slicing)]
+    fn byte_index(&self, pos: usize) -> usize {
+        if pos >= self.len {
+            return self.string.len();
+        }
+        match &self.graphemes {
+            Some(graphemes) => graphemes[pos],
+            None => pos,
+        }
+    }
+
+    fn grapheme_index(&self, byte_index: usize) -> usize {
+        if byte_index >= self.string.len() {
+            return self.len;
+        }
+        match &self.graphemes {
+            Some(graphemes) => graphemes
+                .binary_search(&byte_index)
+                .unwrap_or_else(|index| index.saturating_sub(1)),
+            None => byte_index,
+        }
+    }
+
+    fn reindex(&mut self) {
+        if is_plain(&self.string) {
+            self.graphemes = None;
+            self.len = self.string.len();
+        } else {
+            let graphemes: Vec<usize> = self.string.grapheme_indices(true).map(|(index, _)| index).collect();
+            self.len = graphemes.len();
+            self.graphemes = Some(graphemes);
+        }
+    }
+
+    // Replaces the graphemes in `start..end` with `text`, re-segmenting only the
+    // graphemes around the edit instead of the whole row.
+    #[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing, clippy::string_slice)]
+    fn splice(&mut self, start: usize, end: usize, text: &str) {
+        let start_byte = self.byte_index(start);
+        let end_byte = self.byte_index(end);
+        self.string.replace_range(start_byte..end_byte, text);
+
+        let Some(graphemes) = &mut self.graphemes else {
+            // Plain text may still form a CRLF grapheme with a neighbouring
+            // `\r` or `\n`.
+            let around = start_byte.saturating_sub(1)..cmp::min(start_byte + text.len() + 1, self.string.len());
+            let around = &self.string.as_bytes()[around];
+            if is_plain(text) && !around.windows(2).any(|pair| pair == b"\r\n") {
+                self.len = self.len - (end - start) + text.len();
+            } else {
+                self.reindex();
             }
+            return;
+        };
+
+        // Neighbouring graphemes may combine with the new text, so the window
+        // spans from the grapheme before the edit to the one after it.
+        let window_start = start.saturating_sub(1);
+        let window_end = cmp::min(end + 1, graphemes.len());
+        let shift = |byte: usize| byte + text.len() - (end_byte - start_byte);
+        let byte_start = graphemes.get(window_start).copied().unwrap_or(0);
+        let byte_end = graphemes.get(window_end).map_or(self.string.len(), |&byte| shift(byte));
+
+        for byte in &mut graphemes[window_end..] {
+            *byte = shift(*byte);
         }
-        None
+        let window = self.string[byte_start..byte_end]
+            .grapheme_indices(true)
+            .map(|(index, _)| byte_start + index);
+        graphemes.splice(window_start..window_end, window);
+        self.len = graphemes.len();
     }
-}
\ No newline at end of file
+}
+
+// Columns taken by `grapheme` when drawn at render column `column`: a tab
+// reaches the next tab stop, East Asian wide characters and most emoji take
+// two columns and combining marks none.
+#[allow(clippy::arithmetic_side_effects)]
+fn grapheme_width(grapheme: &str, column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = cmp::max(tab_width, 1);
+        tab_width - column % tab_width
+    } else if let Some(symbol) = control_symbol(grapheme) {
+        symbol.len()
+    } else if grapheme.is_ascii() {
+        1
+    } else {
+        grapheme.width()
+    }
+}
+
+// What is drawn for a control character, which must not reach the terminal:
+// `^X` for ASCII ones and `?` for the rest.
+fn control_symbol(grapheme: &str) -> Option<String> {
+    let c = grapheme.chars().next().filter(|c| c.is_control())?;
+    if c.is_ascii() {
+        let byte = u8::try_from(c).ok()?;
+        Some(format!("^{}", char::from(byte ^ 0x40)))
+    } else {
+        Some("?".to_owned())
+    }
+}
+
+fn is_plain(text: &str) -> bool {
+    text.is_ascii() && !text.contains("\r\n")
+}
+
+fn is_separator(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_none_or(|c| c != '_' && (c.is_ascii_punctuation() || c.is_whitespace()))
+}
+
+fn is_number(grapheme: &str, previous: highlighting::Type, follows_separator: bool) -> bool {
+    let Some(c) = grapheme.chars().next() else {
+        return false;
+    };
+    if previous == highlighting::Type::Number {
+        c.is_ascii_alphanumeric() || c == '.' || c == '_'
+    } else {
+        c.is_ascii_digit() && follows_separator
+    }
+}
+
+// Length of a character literal such as `'a'` or `'\n'` starting at `index`.
+#[allow(clippy::arithmetic_side_effects)]
+fn character_len(opts: &HighlightingOptions, graphemes: &[(usize, &str)], index: usize) -> Option<usize> {
+    let grapheme = |offset: usize| graphemes.get(index + offset).map(|&(_, grapheme)| grapheme);
+    if !opts.characters() || grapheme(0) != Some("'") {
+        return None;
+    }
+    let closing = if grapheme(1) == Some("\\") { 3 } else { 2 };
+    (grapheme(closing) == Some("'")).then_some(closing + 1)
+}
+
+// Length of the string starting at `index`, up to its closing delimiter or the
+// end of the row.
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+fn string_len(graphemes: &[(usize, &str)], index: usize) -> usize {
+    let delimiter = graphemes[index].1;
+    let mut end = index + 1;
+    while let Some(&(_, grapheme)) = graphemes.get(end) {
+        end += 1;
+        if grapheme == "\\" {
+            end += 1;
+        } else if grapheme == delimiter {
+            break;
+        }
+    }
+    cmp::min(end, graphemes.len()) - index
+}
+
+fn keyword_len(opts: &HighlightingOptions, rest: &str) -> Option<(highlighting::Type, usize)> {
+    let keywords = opts
+        .primary_keywords()
+        .iter()
+        .map(|keyword| (highlighting::Type::PrimaryKeywords, keyword))
+        .chain(
+            opts.secondary_keywords()
+                .iter()
+                .map(|keyword| (highlighting::Type::SecondaryKeywords, keyword)),
+        );
+    for (kind, keyword) in keywords {
+        if let Some(after) = rest.strip_prefix(keyword) {
+            if after.graphemes(true).next().is_none_or(is_separator) {
+                return Some((kind, keyword.len()));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+
+    // Checks the row against one segmented from scratch.
+    fn assert_indexed(row: &Row, expected: &str) {
+        let graphemes: Vec<&str> = expected.graphemes(true).collect();
+        assert_eq!(row.text(0..row.len()), expected);
+        assert_eq!(row.len(), graphemes.len());
+        for (index, grapheme) in graphemes.iter().enumerate() {
+            assert_eq!(row.text(index..index + 1), *grapheme);
+        }
+    }
+
+    #[test]
+    fn splice_keeps_grapheme_offsets() {
+        let mut row = Row::from("naïve café");
+        row.insert_str(2, "ö");
+        assert_indexed(&row, "naöïve café");
+        row.delete(0);
+        assert_indexed(&row, "aöïve café");
+        assert_eq!(row.remove(4..6), "e ");
+        assert_indexed(&row, "aöïvcafé");
+        row.insert_str(99, "!");
+        assert_indexed(&row, "aöïvcafé!");
+        row.append(&Row::from("日本"));
+        assert_indexed(&row, "aöïvcafé!日本");
+        let tail = row.split(5);
+        assert_indexed(&row, "aöïvc");
+        assert_indexed(&tail, "afé!日本");
+    }
+
+    #[test]
+    fn splice_regroups_graphemes_at_its_edges() {
+        let mut row = Row::from("ab");
+        assert_eq!(row.insert_str(1, "\u{301}"), 0..1);
+        assert_indexed(&row, "a\u{301}b");
+        assert_eq!(row.insert_str(2, "\u{1f44d}\u{1f3fd}"), 2..3);
+        assert_indexed(&row, "a\u{301}b\u{1f44d}\u{1f3fd}");
+        // Removing the letter leaves the mark on its own.
+        assert_eq!(row.remove(0..1), "a\u{301}");
+        row.insert_str(0, "\u{301}");
+        assert_indexed(&row, "\u{301}b\u{1f44d}\u{1f3fd}");
+        assert_eq!(row.insert_str(0, "e"), 0..1);
+        assert_indexed(&row, "e\u{301}b\u{1f44d}\u{1f3fd}");
+    }
+
+    #[test]
+    fn splice_joins_crlf() {
+        // Plain rows become one grapheme shorter when `\r` meets `\n`.
+        let mut row = Row::from("a\rb\nc");
+        assert_indexed(&row, "a\rb\nc");
+        row.delete(2);
+        assert_indexed(&row, "a\r\nc");
+        assert_eq!(row.len(), 3);
+        // Text after it starts after the pair.
+        assert_eq!(row.insert_str(2, "x"), 2..3);
+        assert_indexed(&row, "a\r\nxc");
+
+        // So do rows that are not plain.
+        let mut row = Row::from("é\r");
+        row.insert_str(2, "\n");
+        assert_indexed(&row, "é\r\n");
+        assert_eq!(row.remove(1..2), "\r\n");
+        assert_indexed(&row, "é");
+        row.insert_str(1, "\n");
+        row.insert_str(1, "\r");
+        assert_indexed(&row, "é\r\n");
+    }
+
+    #[test]
+    fn find_backward_steps_through_the_forward_matches() {
+        let row = Row::from("a".repeat(2000).as_str());
+        let query = Query::new("aa", SearchOptions::default()).unwrap();
+        assert_eq!(row.matches(&query).count(), 1000);
+        assert_eq!(row.find_backward(&query, usize::MAX), Some((1998, 2)));
+        assert_eq!(row.find_backward(&query, 1999), Some((1998, 2)));
+        assert_eq!(row.find_backward(&query, 1998), Some((1996, 2)));
+        assert_eq!(row.find_backward(&query, 0), None);
+
+        // Empty matches, one per grapheme and at the end.
+        let row = Row::from("né");
+        let query = Query::new("x*", SearchOptions { regex: true, ..SearchOptions::default() }).unwrap();
+        let forward: Vec<(usize, usize)> = row.matches(&query).collect();
+        assert_eq!(forward, [(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(row.find_backward(&query, usize::MAX), Some((2, 0)));
+        assert_eq!(row.find_backward(&query, 2), Some((1, 0)));
+    }
+
+    #[test]
+    fn insert_returns_the_graphemes_holding_the_text() {
+        let mut row = Row::from("abc");
+        assert_eq!(row.insert_str(1, "xy"), 1..3);
+        assert_eq!(row.insert_str(9, "z"), 5..6);
+        assert_eq!(row.insert_str(0, ""), 0..0);
+        assert_indexed(&row, "axybcz");
+    }
+}
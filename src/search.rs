@@ -0,0 +1,142 @@
+use regex::{Regex, RegexBuilder};
+
+use std::ops::Range;
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub ignore_case: bool,
+    pub whole_word: bool,
+}
+
+impl SearchOptions {
+    // Describes the active flags for the search prompt, e.g. "regex, whole word".
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let flags: Vec<&str> = [
+            (self.regex, "regex"),
+            (self.ignore_case, "ignore case"),
+            (self.whole_word, "whole word"),
+        ]
+        .iter()
+        .filter(|(active, _)| *active)
+        .map(|&(_, name)| name)
+        .collect();
+        flags.join(", ")
+    }
+}
+
+#[derive(Clone)]
+pub struct Query {
+    regex: Regex,
+}
+
+impl Query {
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(text: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        let mut pattern = if options.regex {
+            text.to_owned()
+        } else {
+            regex::escape(text)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.ignore_case)
+            .build()?;
+        Ok(Self { regex })
+    }
+
+    // Byte range of the first match in `haystack` starting at or after `start`.
+    #[must_use]
+    pub fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        self.regex.find_at(haystack, start).map(|found| found.range())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Start and end bytes of each match, stepping past the previous one.
+    fn find_all(text: &str, options: SearchOptions, haystack: &str) -> Vec<(usize, usize)> {
+        let query = Query::new(text, options).unwrap();
+        let mut found = Vec::new();
+        let mut start = 0;
+        while let Some(range) = query.find_at(haystack, start) {
+            start = range.end.max(range.start + 1);
+            found.push((range.start, range.end));
+            if start > haystack.len() {
+                break;
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn plain_text_matches_literally() {
+        let plain = SearchOptions::default();
+        assert_eq!(find_all("a.b", plain, "axb a.b"), [(4, 7)]);
+        assert_eq!(find_all("(x)*", plain, "(x)* x"), [(0, 4)]);
+        assert_eq!(find_all("\\d", plain, "1 \\d"), [(2, 4)]);
+        assert_eq!(find_all("Ab", plain, "ab Ab AB"), [(3, 5)]);
+    }
+
+    #[test]
+    fn regex_mode_uses_the_pattern() {
+        let regex = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(find_all("a.b", regex, "axb a.b"), [(0, 3), (4, 7)]);
+        assert_eq!(find_all(r"\d+", regex, "a 12 b 3"), [(2, 4), (7, 8)]);
+        assert!(Query::new("(", regex).is_err());
+        assert!(Query::new("(", SearchOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn ignore_case_folds_letters() {
+        let options = SearchOptions {
+            ignore_case: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(find_all("ab", options, "ab Ab AB"), [(0, 2), (3, 5), (6, 8)]);
+        assert_eq!(find_all("straße", options, "STRASSE Straße"), [(8, 15)]);
+    }
+
+    #[test]
+    fn whole_word_needs_word_boundaries() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(find_all("cat", options, "cat concat cats cat_ (cat)"), [(0, 3), (22, 25)]);
+        // Alternatives in a regex stay inside the boundaries.
+        let options = SearchOptions {
+            regex: true,
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(find_all("a|b", options, "ab a b"), [(3, 4), (5, 6)]);
+    }
+
+    #[test]
+    fn find_at_starts_at_the_given_byte() {
+        let query = Query::new("é", SearchOptions::default()).unwrap();
+        assert_eq!(query.find_at("éaé", 0), Some(0..2));
+        assert_eq!(query.find_at("éaé", 2), Some(3..5));
+        assert_eq!(query.find_at("éaé", 5), None);
+    }
+
+    #[test]
+    fn describe_lists_the_active_flags() {
+        assert_eq!(SearchOptions::default().describe(), "");
+        let options = SearchOptions {
+            regex: true,
+            ignore_case: false,
+            whole_word: true,
+        };
+        assert_eq!(options.describe(), "regex, whole word");
+    }
+}
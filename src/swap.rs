@@ -0,0 +1,81 @@
+use super::atomic;
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Swap files keep the unsaved text of a document next to its file, named
+// `.name.hecto.swp`, so edits survive a crash. The text follows this header
+// and a line with the id of the process that wrote it, which tells the swap
+// file of a running session from one left behind by a crash.
+const HEADER: &str = "hecto swap file\n";
+const PID: &str = "pid ";
+
+#[must_use]
+pub fn path(file_name: &str) -> PathBuf {
+    let path = Path::new(file_name);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.hecto.swp", name))
+}
+
+// Writes the swap file of `file_name`, no more readable than the file itself,
+// or only by the user while the file does not exist.
+pub fn write<F>(file_name: &str, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    let mode = fs::metadata(file_name).map_or(0o600, |metadata| atomic::mode(&metadata) & 0o666);
+    atomic::write_with_mode(&path(file_name), mode, |file| {
+        file.write_all(HEADER.as_bytes())?;
+        writeln!(file, "{}{}", PID, process::id())?;
+        write(file)
+    })
+}
+
+pub fn read(path: &Path) -> Result<String, Error> {
+    let contents = fs::read_to_string(path)?;
+    split(&contents)
+        .map(|(_, text)| text.to_owned())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "not a hecto swap file"))
+}
+
+// The id of the process that wrote the swap file at `path`, if it is still
+// running.
+#[must_use]
+pub fn running_owner(path: &Path) -> Option<u32> {
+    let mut head = Vec::new();
+    File::open(path).ok()?.take(64).read_to_end(&mut head).ok()?;
+    let head = String::from_utf8_lossy(&head);
+    let (pid, _) = split(&head)?;
+    (pid != process::id() && is_running(pid)).then_some(pid)
+}
+
+pub fn remove(path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+// Splits the contents of a swap file into the id of its writer and the text.
+fn split(contents: &str) -> Option<(u32, &str)> {
+    let (line, text) = contents.strip_prefix(HEADER)?.split_once('\n')?;
+    let pid = line.strip_prefix(PID)?.parse().ok()?;
+    Some((pid, text))
+}
+
+// Whether a process with id `pid` exists, which is only known where `/proc`
+// lists them.
+#[cfg(target_os = "linux")]
+fn is_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
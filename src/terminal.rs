@@ -1,6 +1,10 @@
+use super::clipboard;
 use super::Position;
 
 use std::io::{self, stdout, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::{IntoRawMode, RawTerminal};
@@ -10,36 +14,116 @@ pub struct Size {
     pub height: u16,
 }
 
+// How often the terminal size is checked while waiting for input.
+const RESIZE_POLL: Duration = Duration::from_millis(100);
+
+pub enum Event {
+    Key(Key),
+    Resize,
+    // No input arrived for a while.
+    Idle,
+}
+
+// Everything the editor needs from a terminal. Output methods take `&self`
+// since, like stdout, a backend is a shared sink.
+pub trait Backend {
+    // Size of the text area, which excludes the status and message bars.
+    fn size(&self) -> &Size;
+
+    // Waits for the next key, or returns `Event::Resize` once the size changes
+    // and `Event::Idle` whenever input pauses.
+    #[allow(clippy::missing_errors_doc)]
+    fn read_event(&mut self) -> Result<Event, std::io::Error>;
+
+    fn write(&self, text: &str);
+
+    fn cursor_position(&self, position: &Position);
+
+    fn cursor_hide(&self);
+
+    fn cursor_show(&self);
+
+    fn clear_screen(&self);
+
+    #[allow(clippy::missing_errors_doc)]
+    fn flush(&self) -> Result<(), std::io::Error>;
+
+    // Asks the terminal to put `text` on the system clipboard using OSC 52,
+    // which also works over SSH. Returns false if the payload exceeds `limit`.
+    fn copy_to_clipboard(&self, text: &str, limit: usize) -> bool {
+        let Some(sequence) = clipboard::osc52(text, limit) else {
+            return false;
+        };
+        self.write(&sequence);
+        true
+    }
+}
+
 pub struct Terminal {
     size: Size,
     _stdout: RawTerminal<std::io::Stdout>,
+    keys: Receiver<Result<Key, std::io::Error>>,
 }
 
 impl Terminal {
-    #[allow(clippy::missing_errors_doc)]
+    #[allow(clippy::missing_errors_doc, clippy::should_implement_trait)]
     pub fn default() -> Result<Self, std::io::Error> {
         let size = termion::terminal_size()?;
 
+        // Keys are read on their own thread so waiting for input never blocks
+        // noticing a resize.
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            for key in io::stdin().keys() {
+                if sender.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+
         Ok(Self {
-            size: Size {
-                width: size.0,
-                height: size.1.saturating_sub(2),
-            },
+            size: Self::size_from(size),
             _stdout: stdout().into_raw_mode()?,
+            keys,
         })
     }
 
-    #[must_use]
-    pub fn size(&self) -> &Size {
+    fn size_from((width, height): (u16, u16)) -> Size {
+        Size {
+            width,
+            height: height.saturating_sub(2),
+        }
+    }
+}
+
+impl Backend for Terminal {
+    fn size(&self) -> &Size {
         &self.size
     }
 
-    pub fn clear_screen() {
-        print!("{}", termion::clear::All);
+    fn read_event(&mut self) -> Result<Event, std::io::Error> {
+        match self.keys.recv_timeout(RESIZE_POLL) {
+            Ok(key) => key.map(Event::Key),
+            Err(RecvTimeoutError::Timeout) => {
+                let size = Self::size_from(termion::terminal_size()?);
+                if size.width == self.size.width && size.height == self.size.height {
+                    return Ok(Event::Idle);
+                }
+                self.size = size;
+                Ok(Event::Resize)
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input closed"))
+            },
+        }
     }
 
-    #[allow(clippy::cast_possible_truncation, clippy::missing_panics_doc)]
-    pub fn cursor_position(position: &Position) {
+    fn write(&self, text: &str) {
+        print!("{}", text);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn cursor_position(&self, position: &Position) {
         let Position{ mut x, mut y } = position;
         x = x.saturating_add(1);
         y = y.saturating_add(1);
@@ -50,37 +134,19 @@ impl Terminal {
         print!("{}", termion::cursor::Goto(x, y));
     }
 
-    #[allow(clippy::missing_errors_doc)]
-    pub fn flush() -> Result<(), std::io::Error> {
-        io::stdout().flush()
-    }
-
-    #[allow(clippy::missing_errors_doc)]
-    pub fn read_key() -> Result<Key, std::io::Error> {
-        loop {
-            if let Some(key) = io::stdin().lock().keys().next() {
-                return key;
-            }
-        }
-    }
-
-    pub fn cursor_hide() {
+    fn cursor_hide(&self) {
         print!("{}", termion::cursor::Hide);
     }
 
-    pub fn cursor_show() {
+    fn cursor_show(&self) {
         print!("{}", termion::cursor::Show);
     }
 
-    pub fn clear_current_line() {
-        print!("{}", termion::clear::CurrentLine);
-    }
-
-    pub fn invert_style() {
-        print!("{}", termion::style::Invert);
+    fn clear_screen(&self) {
+        print!("{}", termion::clear::All);
     }
 
-    pub fn reset_style() {
-        print!("{}", termion::style::Reset);
+    fn flush(&self) -> Result<(), std::io::Error> {
+        io::stdout().flush()
     }
-}
\ No newline at end of file
+}
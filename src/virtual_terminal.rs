@@ -0,0 +1,351 @@
+use super::clipboard;
+use super::terminal::{Backend, Event, Size};
+use super::Position;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+use termion::event::Key;
+use unicode_width::UnicodeWidthChar;
+
+enum Scripted {
+    Key(Key),
+    Resize(u16, u16),
+    Idle,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cell {
+    // Empty for the second column of a wide character.
+    pub symbol: String,
+    pub inverted: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".to_owned(),
+            inverted: false,
+        }
+    }
+}
+
+struct Screen {
+    size: Size,
+    cells: Vec<Vec<Cell>>,
+    cursor: Position,
+    cursor_visible: bool,
+    inverted: bool,
+    // Bytes of an escape sequence that is still being written.
+    pending: String,
+    events: VecDeque<Scripted>,
+    clipboard: Option<String>,
+    written: usize,
+}
+
+// An in-memory terminal that records what is drawn into a grid of cells and
+// replays scripted keys, so the editor can be driven without a TTY. Clones
+// share the same screen, letting a caller inspect it after handing one to
+// `Editor::new`.
+#[derive(Clone)]
+pub struct VirtualTerminal {
+    screen: Rc<RefCell<Screen>>,
+    size: Rc<Size>,
+}
+
+impl VirtualTerminal {
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        let screen = Screen {
+            size: Size { width, height },
+            cells: Vec::new(),
+            cursor: Position::default(),
+            cursor_visible: true,
+            inverted: false,
+            pending: String::new(),
+            events: VecDeque::new(),
+            clipboard: None,
+            written: 0,
+        };
+        let mut terminal = Self {
+            screen: Rc::new(RefCell::new(screen)),
+            size: Rc::new(text_area(width, height)),
+        };
+        terminal.resize(width, height);
+        terminal
+    }
+
+    pub fn push_keys<I: IntoIterator<Item = Key>>(&self, keys: I) {
+        let mut screen = self.screen.borrow_mut();
+        screen.events.extend(keys.into_iter().map(Scripted::Key));
+    }
+
+    pub fn push_str(&self, text: &str) {
+        self.push_keys(text.chars().map(Key::Char));
+    }
+
+    pub fn push_resize(&self, width: u16, height: u16) {
+        self.screen.borrow_mut().events.push_back(Scripted::Resize(width, height));
+    }
+
+    // Simulates a pause in the input.
+    pub fn push_idle(&self) {
+        self.screen.borrow_mut().events.push_back(Scripted::Idle);
+    }
+
+    // The text on screen row `y` with trailing blanks removed.
+    #[must_use]
+    pub fn line(&self, y: usize) -> String {
+        let screen = self.screen.borrow();
+        let line: String = screen
+            .cells
+            .get(y)
+            .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+            .unwrap_or_default();
+        line.trim_end().to_owned()
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        let height = self.screen.borrow().cells.len();
+        (0..height).map(|y| self.line(y)).collect()
+    }
+
+    #[must_use]
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        self.screen.borrow().cells.get(y).and_then(|row| row.get(x)).cloned()
+    }
+
+    #[must_use]
+    pub fn cursor(&self) -> Position {
+        self.screen.borrow().cursor.clone()
+    }
+
+    #[must_use]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.screen.borrow().cursor_visible
+    }
+
+    // Bytes written so far, counting escape sequences.
+    #[must_use]
+    pub fn bytes_written(&self) -> usize {
+        self.screen.borrow().written
+    }
+
+    // The last text sent to the system clipboard.
+    #[must_use]
+    pub fn clipboard(&self) -> Option<String> {
+        self.screen.borrow().clipboard.clone()
+    }
+
+    fn resize(&mut self, width: u16, height: u16) {
+        let mut screen = self.screen.borrow_mut();
+        screen.size = Size { width, height };
+        screen
+            .cells
+            .resize(usize::from(height), vec![Cell::default(); usize::from(width)]);
+        for row in &mut screen.cells {
+            row.resize(usize::from(width), Cell::default());
+        }
+        self.size = Rc::new(text_area(width, height));
+    }
+}
+
+fn text_area(width: u16, height: u16) -> Size {
+    Size {
+        width,
+        height: height.saturating_sub(2),
+    }
+}
+
+impl Screen {
+    // Writes `symbol` at the cursor; wide characters also cover the next cell
+    // and zero-width ones combine with the previous cell.
+    fn put(&mut self, symbol: char) {
+        let Position { x, y } = self.cursor;
+        let inverted = self.inverted;
+        let width = symbol.width().unwrap_or(0);
+        let Some(row) = self.cells.get_mut(y) else {
+            return;
+        };
+        if width == 0 {
+            if let Some(cell) = x.checked_sub(1).and_then(|x| row.get_mut(x)) {
+                cell.symbol.push(symbol);
+            }
+            return;
+        }
+        if let Some(cell) = row.get_mut(x) {
+            *cell = Cell {
+                symbol: symbol.to_string(),
+                inverted,
+            };
+        }
+        for x in x.saturating_add(1)..x.saturating_add(width) {
+            if let Some(cell) = row.get_mut(x) {
+                *cell = Cell {
+                    symbol: String::new(),
+                    inverted,
+                };
+            }
+        }
+        self.cursor.x = x.saturating_add(width);
+    }
+
+    fn clear_line(&mut self, y: usize) {
+        if let Some(row) = self.cells.get_mut(y) {
+            row.fill(Cell::default());
+        }
+    }
+
+    fn clear(&mut self) {
+        for row in &mut self.cells {
+            row.fill(Cell::default());
+        }
+    }
+
+    // Handles a Control Sequence Introducer given its parameters and final byte.
+    fn control_sequence(&mut self, parameters: &str, command: char) {
+        let number = |index: usize| {
+            parameters
+                .split(';')
+                .nth(index)
+                .and_then(|parameter| parameter.parse::<usize>().ok())
+                .unwrap_or(0)
+        };
+        match (command, parameters) {
+            ('m', _) => self.select_graphic_rendition(parameters),
+            ('H', _) => {
+                self.cursor = Position {
+                    x: number(1).saturating_sub(1),
+                    y: number(0).saturating_sub(1),
+                };
+            },
+            ('K', "" | "0") => {
+                let Position { x, y } = self.cursor;
+                if let Some(row) = self.cells.get_mut(y) {
+                    row.iter_mut().skip(x).for_each(|cell| *cell = Cell::default());
+                }
+            },
+            ('K', "2") => self.clear_line(self.cursor.y),
+            ('J', "2") => self.clear(),
+            ('l', "?25") => self.cursor_visible = false,
+            ('h', "?25") => self.cursor_visible = true,
+            _ => (),
+        }
+    }
+
+    // Applies a Select Graphic Rendition sequence; only inversion is tracked.
+    fn select_graphic_rendition(&mut self, parameters: &str) {
+        let mut parameters = parameters.split(';');
+        while let Some(parameter) = parameters.next() {
+            match parameter {
+                "" | "0" | "27" => self.inverted = false,
+                "7" => self.inverted = true,
+                // Extended colors carry their own parameters.
+                "38" | "48" => {
+                    let skip = if parameters.next() == Some("2") { 3 } else { 1 };
+                    for _ in 0..skip {
+                        parameters.next();
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.written = self.written.saturating_add(text.len());
+        for c in text.chars() {
+            if !self.pending.is_empty() {
+                self.pending.push(c);
+                self.escape_sequence();
+                continue;
+            }
+            match c {
+                '\x1b' => self.pending.push(c),
+                '\r' => self.cursor.x = 0,
+                '\n' => {
+                    let last = usize::from(self.size.height).saturating_sub(1);
+                    self.cursor.y = self.cursor.y.saturating_add(1).min(last);
+                },
+                c if c.is_control() => (),
+                c => self.put(c),
+            }
+        }
+    }
+
+    // Consumes the pending escape sequence once it is complete.
+    #[allow(clippy::string_slice)]
+    fn escape_sequence(&mut self) {
+        let sequence = &self.pending;
+        let complete = match sequence.chars().nth(1) {
+            None => false,
+            // Control Sequence Introducer, ended by a byte in `@`..=`~`.
+            Some('[') => sequence.len() > 2 && sequence.ends_with(|c| ('@'..='~').contains(&c)),
+            // Operating System Command, ended by BEL or ST.
+            Some(']') => sequence.ends_with('\x07') || sequence.ends_with("\x1b\\"),
+            Some(_) => true,
+        };
+        if !complete {
+            return;
+        }
+        let sequence = std::mem::take(&mut self.pending);
+        if let Some(body) = sequence.strip_prefix("\x1b[") {
+            if let Some(command) = body.chars().last() {
+                self.control_sequence(&body[..body.len().saturating_sub(command.len_utf8())], command);
+            }
+        }
+    }
+}
+
+impl Backend for VirtualTerminal {
+    fn size(&self) -> &Size {
+        &self.size
+    }
+
+    fn read_event(&mut self) -> Result<Event, std::io::Error> {
+        let event = self.screen.borrow_mut().events.pop_front();
+        match event {
+            Some(Scripted::Key(key)) => Ok(Event::Key(key)),
+            Some(Scripted::Resize(width, height)) => {
+                self.resize(width, height);
+                Ok(Event::Resize)
+            },
+            Some(Scripted::Idle) => Ok(Event::Idle),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more scripted keys")),
+        }
+    }
+
+    fn write(&self, text: &str) {
+        self.screen.borrow_mut().write(text);
+    }
+
+    fn cursor_position(&self, position: &Position) {
+        self.screen.borrow_mut().cursor = position.clone();
+    }
+
+    fn cursor_hide(&self) {
+        self.screen.borrow_mut().cursor_visible = false;
+    }
+
+    fn cursor_show(&self) {
+        self.screen.borrow_mut().cursor_visible = true;
+    }
+
+    fn clear_screen(&self) {
+        self.screen.borrow_mut().clear();
+    }
+
+    fn flush(&self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn copy_to_clipboard(&self, text: &str, limit: usize) -> bool {
+        if clipboard::osc52(text, limit).is_none() {
+            return false;
+        }
+        self.screen.borrow_mut().clipboard = Some(text.to_owned());
+        true
+    }
+}
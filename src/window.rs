@@ -0,0 +1,195 @@
+use super::Position;
+
+use unicode_width::UnicodeWidthChar;
+
+// What the character between two windows side by side looks like.
+const SEPARATOR: &str = "\u{2502}";
+
+// A view of one buffer with its own cursor and scroll offset.
+#[derive(Default, Clone)]
+pub struct Window {
+    // Index of the buffer shown.
+    pub buffer: usize,
+    pub cursor_position: Position,
+    pub offset: Position,
+    pub text_position: Position,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// How the screen is shared between windows: a single window, or two layouts
+// either stacked or side by side.
+pub enum Layout {
+    Window(usize),
+    Split {
+        side_by_side: bool,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    // Splits `window` in two, placing `new` below or to the right of it.
+    pub fn split(&mut self, window: usize, new: usize, side_by_side: bool) {
+        match self {
+            Self::Window(index) if *index == window => {
+                *self = Self::Split {
+                    side_by_side,
+                    first: Box::new(Self::Window(window)),
+                    second: Box::new(Self::Window(new)),
+                };
+            },
+            Self::Window(_) => (),
+            Self::Split { first, second, .. } => {
+                first.split(window, new, side_by_side);
+                second.split(window, new, side_by_side);
+            },
+        }
+    }
+
+    // Removes `window`, giving its space to its neighbour, and renumbers the
+    // windows after it.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn remove(&mut self, window: usize) {
+        match self {
+            Self::Window(index) => {
+                if *index > window {
+                    *index -= 1;
+                }
+            },
+            Self::Split { first, second, .. } => {
+                let remaining = if matches!(**first, Self::Window(index) if index == window) {
+                    Some(std::mem::replace(second, Box::new(Self::Window(0))))
+                } else if matches!(**second, Self::Window(index) if index == window) {
+                    Some(std::mem::replace(first, Box::new(Self::Window(0))))
+                } else {
+                    None
+                };
+                if let Some(remaining) = remaining {
+                    *self = *remaining;
+                    self.remove(window);
+                } else {
+                    first.remove(window);
+                    second.remove(window);
+                }
+            },
+        }
+    }
+
+    // Places the windows in `area`, returning the area of each and those of
+    // the separators between windows side by side.
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    pub fn arrange(&self, area: Rect, windows: &mut Vec<(usize, Rect)>, separators: &mut Vec<Rect>) {
+        match self {
+            Self::Window(index) => windows.push((*index, area)),
+            Self::Split {
+                side_by_side: true,
+                first,
+                second,
+            } => {
+                let width = area.width.saturating_sub(1) / 2;
+                let separator = Rect {
+                    x: area.x + width,
+                    width: area.width.min(1),
+                    ..area
+                };
+                first.arrange(Rect { width, ..area }, windows, separators);
+                second.arrange(
+                    Rect {
+                        x: separator.x + separator.width,
+                        width: area.width - width - separator.width,
+                        ..area
+                    },
+                    windows,
+                    separators,
+                );
+                separators.push(separator);
+            },
+            Self::Split {
+                side_by_side: false,
+                first,
+                second,
+            } => {
+                let height = area.height.div_ceil(2);
+                first.arrange(Rect { height, ..area }, windows, separators);
+                second.arrange(
+                    Rect {
+                        y: area.y + height,
+                        height: area.height - height,
+                        ..area
+                    },
+                    windows,
+                    separators,
+                );
+            },
+        }
+    }
+}
+
+// Joins the lines drawn for each area into the lines of the screen, `height`
+// lines in all.
+#[must_use]
+pub fn compose(height: usize, mut pieces: Vec<(Rect, Vec<String>)>, separators: &[Rect]) -> Vec<String> {
+    for separator in separators {
+        pieces.push((*separator, vec![SEPARATOR.to_owned(); separator.height]));
+    }
+    pieces.sort_by_key(|(area, _)| area.x);
+    (0..height)
+        .map(|y| {
+            let mut line = String::new();
+            let mut column = 0;
+            for (area, lines) in &pieces {
+                let Some(text) = y.checked_sub(area.y).and_then(|index| lines.get(index)) else {
+                    continue;
+                };
+                if y >= area.y.saturating_add(area.height) {
+                    continue;
+                }
+                line.push_str(&" ".repeat(area.x.saturating_sub(column)));
+                let (text, width) = fit(text, area.width);
+                line.push_str(&text);
+                column = area.x.saturating_add(width);
+            }
+            line
+        })
+        .collect()
+}
+
+// Cuts `line` down to `width` columns, keeping the escape sequences that style
+// it, and returns it with the columns it takes.
+fn fit(line: &str, width: usize) -> (String, usize) {
+    let mut result = String::with_capacity(line.len());
+    let mut columns: usize = 0;
+    let mut full = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            result.push(c);
+            // Control Sequence Introducer, ended by a byte in `@`..=`~`.
+            if let Some(c) = chars.next() {
+                result.push(c);
+                for c in chars.by_ref() {
+                    result.push(c);
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        // Leave out the rest of the text but not how it is styled.
+        let next = columns.saturating_add(c.width().unwrap_or(0));
+        full = full || next > width;
+        if !full {
+            result.push(c);
+            columns = next;
+        }
+    }
+    (result, columns)
+}
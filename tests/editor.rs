@@ -0,0 +1,260 @@
+use hecto::{Config, Editor, VirtualTerminal};
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use termion::event::Key;
+
+const WIDTH: u16 = 80;
+const HEIGHT: u16 = 10;
+
+// A directory of its own for each test, removed when the test ends.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("hecto-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("scratch directory");
+        Self(path)
+    }
+
+    fn file(&self, name: &str, contents: &str) -> String {
+        let path = self.0.join(name);
+        fs::write(&path, contents).expect("scratch file");
+        path.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// Runs the editor on `file` until `script` runs out of input, and returns
+// the terminal it drew on.
+fn run(file: &str, script: impl FnOnce(&VirtualTerminal)) -> VirtualTerminal {
+    let terminal = VirtualTerminal::new(WIDTH, HEIGHT);
+    let mut editor = Editor::new(Box::new(terminal.clone()), Config::default(), &[file]);
+    script(&terminal);
+    editor.run();
+    terminal
+}
+
+fn status_bar(terminal: &VirtualTerminal) -> String {
+    terminal.line(usize::from(HEIGHT) - 2)
+}
+
+fn message_bar(terminal: &VirtualTerminal) -> String {
+    terminal.line(usize::from(HEIGHT) - 1)
+}
+
+#[test]
+fn typing_inserts_text_at_the_cursor() {
+    let scratch = Scratch::new("typing");
+    let file = scratch.file("notes.txt", "first\n");
+    let terminal = run(&file, |terminal| {
+        terminal.push_keys([Key::End]);
+        terminal.push_str(" line\nsecond");
+        terminal.push_keys([Key::Left, Key::Left, Key::Backspace]);
+    });
+
+    assert_eq!(terminal.line(0), "first line");
+    assert_eq!(terminal.line(1), "secnd");
+    assert_eq!(terminal.line(2), "~");
+    assert!(status_bar(&terminal).contains("(modified)"));
+    assert_eq!((terminal.cursor().x, terminal.cursor().y), (3, 1));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "first\n");
+}
+
+#[test]
+fn undo_and_redo_restore_the_text() {
+    let scratch = Scratch::new("undo");
+    let file = scratch.file("notes.txt", "one\n");
+    let undone = run(&file, |terminal| {
+        terminal.push_keys([Key::End]);
+        terminal.push_str(" two\nthree");
+        terminal.push_keys([Key::Ctrl('z')]);
+    });
+    assert_eq!(undone.line(0), "one two");
+    assert_eq!(undone.line(1), "");
+    assert_eq!(undone.line(2), "~");
+
+    let all_undone = run(&file, |terminal| {
+        terminal.push_keys([Key::End]);
+        terminal.push_str(" two\nthree");
+        terminal.push_keys([Key::Ctrl('z'), Key::Ctrl('z'), Key::Ctrl('z'), Key::Ctrl('z')]);
+    });
+    assert_eq!(all_undone.line(0), "one");
+    assert!(!status_bar(&all_undone).contains("(modified)"));
+    assert_eq!(message_bar(&all_undone), "Nothing to undo.");
+
+    let redone = run(&file, |terminal| {
+        terminal.push_keys([Key::End]);
+        terminal.push_str(" two\nthree");
+        terminal.push_keys([Key::Ctrl('z'), Key::Ctrl('z'), Key::Ctrl('y'), Key::Ctrl('y')]);
+    });
+    assert_eq!(redone.line(0), "one two");
+    assert_eq!(redone.line(1), "three");
+}
+
+#[test]
+fn save_writes_the_file() {
+    let scratch = Scratch::new("save");
+    let file = scratch.file("notes.txt", "alpha\r\nbeta\r\n");
+    let terminal = run(&file, |terminal| {
+        terminal.push_keys([Key::Down, Key::End]);
+        terminal.push_str("!\ngamma");
+        terminal.push_keys([Key::Ctrl('s')]);
+    });
+
+    assert_eq!(fs::read_to_string(&file).unwrap(), "alpha\r\nbeta!\r\ngamma\r\n");
+    assert_eq!(message_bar(&terminal), "File saved successfully.");
+    assert!(!status_bar(&terminal).contains("(modified)"));
+}
+
+#[test]
+fn typing_without_pauses_keeps_the_swap_file() {
+    let scratch = Scratch::new("swap");
+    let file = scratch.file("notes.txt", "text\n");
+    let terminal = VirtualTerminal::new(WIDTH, HEIGHT);
+    let config = Config {
+        swap_interval: Duration::ZERO,
+        ..Config::default()
+    };
+    let mut editor = Editor::new(Box::new(terminal.clone()), config, &[&file]);
+    terminal.push_str("more ");
+    editor.run();
+
+    let swap = fs::read_to_string(scratch.0.join(".notes.txt.hecto.swp")).expect("swap file");
+    assert!(swap.ends_with("\nmore text\n"));
+}
+
+#[test]
+fn copying_falls_back_when_the_selection_is_too_large() {
+    let scratch = Scratch::new("copy");
+    let file = scratch.file("notes.txt", "hello\n");
+    let copy = |config: Config| {
+        let terminal = VirtualTerminal::new(WIDTH, HEIGHT);
+        let mut editor = Editor::new(Box::new(terminal.clone()), config, &[&file]);
+        terminal.push_keys([Key::Null, Key::End, Key::Ctrl('c')]);
+        editor.run();
+        terminal
+    };
+
+    let copied = copy(Config::default());
+    assert_eq!(copied.clipboard().as_deref(), Some("hello"));
+    assert_eq!(message_bar(&copied), "Copied selection.");
+
+    let too_large = copy(Config {
+        osc52_limit: 4,
+        ..Config::default()
+    });
+    assert_eq!(too_large.clipboard(), None);
+    assert_eq!(message_bar(&too_large), "Copied selection (too large for the system clipboard).");
+
+    let piped = copy(Config {
+        osc52_limit: 4,
+        clipboard_command: Some("false".to_owned()),
+        ..Config::default()
+    });
+    assert!(message_bar(&piped).starts_with("Copied selection (clipboard command failed: false exited"));
+}
+
+#[test]
+fn search_moves_to_matches_and_counts_them() {
+    let scratch = Scratch::new("search");
+    let file = scratch.file("notes.txt", "a needle\nhay\nmore needles\n");
+    let prompt = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('f')]);
+        terminal.push_str("needle");
+        terminal.push_keys([Key::Right]);
+    });
+    assert!(message_bar(&prompt).starts_with("Search (match 2 of 2)"));
+
+    let found = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('f')]);
+        terminal.push_str("needle");
+        terminal.push_keys([Key::Right, Key::Char('\n')]);
+    });
+    assert_eq!((found.cursor().x, found.cursor().y), (5, 2));
+
+    let cancelled = run(&file, |terminal| {
+        terminal.push_keys([Key::Down, Key::Ctrl('f')]);
+        terminal.push_str("needle");
+        terminal.push_keys([Key::Esc]);
+    });
+    assert_eq!((cancelled.cursor().x, cancelled.cursor().y), (0, 1));
+
+    // Past either end, the search goes on from the other one.
+    let backward = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('f')]);
+        terminal.push_str("needle");
+        terminal.push_keys([Key::Left, Key::Char('\n')]);
+    });
+    assert_eq!((backward.cursor().x, backward.cursor().y), (5, 2));
+    let wrapped = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('f')]);
+        terminal.push_str("needle");
+        terminal.push_keys([Key::Left]);
+    });
+    assert!(message_bar(&wrapped).starts_with("Search wrapped | Search (match 2 of 2)"));
+    let around = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('f')]);
+        terminal.push_str("needle");
+        terminal.push_keys([Key::Right, Key::Right]);
+    });
+    assert!(message_bar(&around).starts_with("Search wrapped | Search (match 1 of 2)"));
+
+    let missing = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('f')]);
+        terminal.push_str("pin");
+    });
+    assert!(message_bar(&missing).starts_with("Search (no matches)"));
+}
+
+#[test]
+fn resize_redraws_at_the_new_size() {
+    let scratch = Scratch::new("resize");
+    let file = scratch.file("notes.txt", "a line that is longer than twenty columns\n");
+    let terminal = run(&file, |terminal| {
+        terminal.push_resize(20, 6);
+        terminal.push_keys([Key::End]);
+    });
+
+    let lines = terminal.lines();
+    assert_eq!(lines.len(), 6);
+    assert!(lines.iter().all(|line| line.chars().count() <= 20));
+    // The view scrolls to keep the cursor at the end of the line visible.
+    assert_eq!(terminal.line(0), "than twenty columns");
+    assert_eq!(terminal.cursor().y, 0);
+    // The status bar moves up to the new second to last line.
+    assert!(terminal.cell(0, 4).is_some_and(|cell| cell.inverted));
+
+    let grown = run(&file, |terminal| {
+        terminal.push_resize(60, 12);
+    });
+    assert_eq!(grown.lines().len(), 12);
+    assert_eq!(grown.line(0), "a line that is longer than twenty columns");
+    assert!(grown.line(10).contains("1:1"));
+}
+
+#[test]
+fn other_windows_follow_edits_to_their_buffer() {
+    let scratch = Scratch::new("windows");
+    let contents: String = (0..10).map(|line| format!("l{}\n", line)).collect();
+    let file = scratch.file("notes.txt", &contents);
+    let terminal = run(&file, |terminal| {
+        terminal.push_keys([Key::Ctrl('w'), Key::Char('s'), Key::Ctrl('w'), Key::Char('w')]);
+        terminal.push_keys([Key::Down; 9]);
+        terminal.push_keys([Key::Ctrl('w'), Key::Char('w'), Key::End]);
+        terminal.push_keys([Key::Delete; 3]);
+    });
+
+    // The upper window scrolled to the end before lines above it were joined,
+    // and still shows the same text.
+    assert_eq!(terminal.line(0), "l6");
+    assert_eq!(terminal.line(3), "l9");
+}